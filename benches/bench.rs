@@ -29,8 +29,8 @@ trait ITestBench {
 impl ITestBench for Actor<TestBench> {
     #[inline]
     async fn add(&self, i: usize) -> Result<()> {
-        self.inner_call(|inner| async move {
-            inner.get_mut().add(i);
+        self.inner_call(async move |mut inner| {
+            inner.add(i);
             Ok(())
         })
         .await
@@ -38,8 +38,8 @@ impl ITestBench for Actor<TestBench> {
 
     #[inline]
     async fn clean(&self) -> Result<()> {
-        self.inner_call(|inner| async move {
-            inner.get_mut().clean();
+        self.inner_call(async move |mut inner| {
+            inner.clean();
             Ok(())
         })
         .await
@@ -54,7 +54,7 @@ impl ITestBench for Actor<TestBench> {
 impl ITestBench for RwModel<TestBench> {
     #[inline]
     async fn add(&self, i: usize) -> Result<()> {
-        self.call_mut(|mut inner| async move {
+        self.call_mut(async move |mut inner| {
             inner.add(i);
             Ok(())
         })
@@ -63,7 +63,7 @@ impl ITestBench for RwModel<TestBench> {
 
     #[inline]
     async fn clean(&self) -> Result<()> {
-        self.call_mut(|mut inner| async move {
+        self.call_mut(async move |mut inner| {
             inner.clean();
             Ok(())
         })
@@ -0,0 +1,51 @@
+use aqueue::{AQueue, Actor, RwModel, RwQueue};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+// the cost of the queue itself, the calls run one by one on one task and never wait
+fn benchmark(c: &mut Criterion) {
+    let size: usize = 100000;
+    let runtime = || tokio::runtime::Builder::new_current_thread().build().unwrap();
+
+    let queue = AQueue::new();
+    c.bench_with_input(BenchmarkId::new("uncontended_aqueue_run", size), &size, |b, &s| {
+        b.to_async(runtime()).iter(|| async {
+            let mut sum = 0;
+            for i in 0..s {
+                sum += queue.run(|x| async move { x }, i).await;
+            }
+            sum
+        });
+    });
+
+    let actor = Actor::new(0usize);
+    c.bench_with_input(BenchmarkId::new("uncontended_actor_inner_call", size), &size, |b, &s| {
+        b.to_async(runtime()).iter(|| async {
+            for i in 0..s {
+                actor.inner_call(async move |mut inner| *inner += i).await;
+            }
+        });
+    });
+
+    let queue = RwQueue::new();
+    c.bench_with_input(BenchmarkId::new("uncontended_rwqueue_read_run", size), &size, |b, &s| {
+        b.to_async(runtime()).iter(|| async {
+            let mut sum = 0;
+            for i in 0..s {
+                sum += queue.read_run(|x| async move { *x }, &i).await;
+            }
+            sum
+        });
+    });
+
+    let model = RwModel::new(0usize);
+    c.bench_with_input(BenchmarkId::new("uncontended_model_call_mut", size), &size, |b, &s| {
+        b.to_async(runtime()).iter(|| async {
+            for i in 0..s {
+                model.call_mut(async move |mut inner| *inner += i).await;
+            }
+        });
+    });
+}
+
+criterion_group!(benches, benchmark);
+criterion_main!(benches);
@@ -37,16 +37,16 @@ trait FooRunner {
 
 impl FooRunner for Actor<Foo> {
     async fn add(&self, x: i32) -> i128 {
-        self.inner_call(|inner| async move { inner.get_mut().add(x) }).await
+        self.inner_call(async move |mut inner| inner.add(x)).await
     }
     async fn reset(&self) {
-        self.inner_call(|inner| async move { inner.get_mut().reset() }).await
+        self.inner_call(async move |mut inner| inner.reset()).await
     }
     async fn get(&self) -> i128 {
-        self.inner_call(|inner| async move { inner.get_mut().get() }).await
+        self.inner_call(async move |inner| inner.get()).await
     }
     async fn get_count(&self) -> u64 {
-        self.inner_call(|inner| async move { inner.get_mut().get_count() }).await
+        self.inner_call(async move |inner| inner.get_count()).await
     }
 }
 
@@ -37,16 +37,16 @@ trait FooRunner {
 
 impl FooRunner for RwModel<Foo> {
     async fn add(&self, x: i32) -> i128 {
-        self.call_mut(|mut inner| async move { inner.add(x) }).await
+        self.call_mut(async move |mut inner| inner.add(x)).await
     }
     async fn reset(&self) {
-        self.call_mut(|mut inner| async move { inner.reset() }).await
+        self.call_mut(async move |mut inner| inner.reset()).await
     }
     async fn get(&self) -> i128 {
-        self.call(|inner| async move { inner.get() }).await
+        self.call(async move |inner| inner.get()).await
     }
     async fn get_count(&self) -> u64 {
-        self.call(|inner| async move { inner.get_count() }).await
+        self.call(async move |inner| inner.get_count()).await
     }
 }
 
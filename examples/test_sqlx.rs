@@ -1,5 +1,5 @@
 use anyhow::{anyhow, Result};
-use aqueue::{inner_wait, Actor};
+use aqueue::Actor;
 use sqlx::sqlite::SqlitePoolOptions;
 use sqlx::SqlitePool;
 use std::env;
@@ -18,9 +18,6 @@ pub struct DataBases {
     pool: SqlitePool,
 }
 
-unsafe impl Send for DataBases {}
-unsafe impl Sync for DataBases {}
-
 impl DataBases {
     pub fn new(sqlite_max_connections: u32) -> Result<Actor<DataBases>> {
         let pool = SqlitePoolOptions::new()
@@ -43,7 +40,7 @@ impl DataBases {
             values(?,?,?)
          "#,
         )
-        .bind(&self.auto_id)
+        .bind(self.auto_id)
         .bind(name)
         .bind(gold)
         .execute(&self.pool)
@@ -69,7 +66,7 @@ pub(crate) trait IDatabase {
     async fn select_all_users(&self) -> Result<Vec<User>>;
     /// ERROR example
     /// call test_unsafe_block thread blocking
-    /// i use timeout to block unlimited thread blocking
+    /// the nested call return Error::Reentrant instead of unlimited blocking
     ///
     ///
     ///       call DB test_unsafe_blocking
@@ -83,7 +80,7 @@ pub(crate) trait IDatabase {
     ///  │                   │
     ///  │                   ▼
     ///  │ call insert_user will lock the current thread again
-    ///  │      current thread unlimited blocking
+    ///  │      return Error::Reentrant, not blocking
     ///  │                   │
     ///  └───────────────────┘
     ///
@@ -92,15 +89,13 @@ pub(crate) trait IDatabase {
 
 impl IDatabase for Actor<DataBases> {
     async fn create_table(&self) -> Result<()> {
-        self.inner_call(|inner| async move { inner.get().create_table().await }).await
+        self.inner_call(async move |inner| inner.create_table().await).await
     }
     async fn insert_user(&self, name: String, gold: f64) -> Result<bool> {
-        self.inner_call(|inner| async move { inner.get_mut().insert_user(&name, gold).await })
-            .await
+        self.inner_call(async move |mut inner| inner.insert_user(&name, gold).await).await
     }
     async fn insert_user_ref_name(&self, name: &str, gold: f64) -> Result<bool> {
-        self.inner_call(|inner| async move { inner.get_mut().insert_user(name, gold).await })
-            .await
+        self.inner_call(async move |mut inner| inner.insert_user(name, gold).await).await
     }
 
     async fn select_all_users(&self) -> Result<Vec<User>> {
@@ -113,7 +108,8 @@ impl IDatabase for Actor<DataBases> {
     }
 
     async fn test_unsafe_blocking(&self, name: String, gold: f64) -> Result<bool> {
-        inner_wait!(self, 3000, |_| async move { DB.insert_user(name, gold).await }).await?
+        self.checked_inner_call(async move |_| DB.checked_inner_call(async move |mut inner| inner.insert_user(&name, gold).await).await?)
+            .await?
     }
 }
 
@@ -134,7 +130,11 @@ async fn main() -> Result<()> {
         let join: JoinHandle<Result<()>> = tokio::spawn(async move {
             //each task runs 1000 times
             for j in 0..1000 {
-                DB.insert_user(i.to_string(), j as f64).await?;
+                if j % 2 == 0 {
+                    DB.insert_user(i.to_string(), j as f64).await?;
+                } else {
+                    DB.insert_user_ref_name(&i.to_string(), j as f64).await?;
+                }
             }
             Ok(())
         });
@@ -150,7 +150,8 @@ async fn main() -> Result<()> {
         println!("{:?}", user);
     }
 
-    DB.test_unsafe_blocking("123123".to_string(), 1111111f64).await?;
+    let err = DB.test_unsafe_blocking("123123".to_string(), 1111111f64).await.unwrap_err();
+    println!("{}", err);
 
     Ok(())
 }
@@ -1,5 +1,5 @@
 use anyhow::{anyhow, Result};
-use aqueue::{call_wait, RwModel};
+use aqueue::RwModel;
 use sqlx::sqlite::SqlitePoolOptions;
 use sqlx::SqlitePool;
 use std::env;
@@ -18,9 +18,6 @@ pub struct DataBases {
     pool: SqlitePool,
 }
 
-unsafe impl Send for DataBases {}
-unsafe impl Sync for DataBases {}
-
 impl DataBases {
     pub fn new(sqlite_max_connections: u32) -> Result<RwModel<DataBases>> {
         let pool = SqlitePoolOptions::new()
@@ -43,7 +40,7 @@ impl DataBases {
             values(?,?,?)
          "#,
         )
-        .bind(&self.auto_id)
+        .bind(self.auto_id)
         .bind(name)
         .bind(gold)
         .execute(&self.pool)
@@ -69,7 +66,7 @@ pub(crate) trait IDatabase {
     async fn select_all_users(&self) -> Result<Vec<User>>;
     /// ERROR example
     /// call test_unsafe_block thread blocking
-    /// i use timeout to block unlimited thread blocking
+    /// the nested call return Error::Reentrant instead of unlimited blocking
     ///
     ///
     ///       call DB test_unsafe_blocking
@@ -83,7 +80,7 @@ pub(crate) trait IDatabase {
     ///  │                   │
     ///  │                   ▼
     ///  │ call insert_user will lock the current thread again
-    ///  │      current thread unlimited blocking
+    ///  │      return Error::Reentrant, not blocking
     ///  │                   │
     ///  └───────────────────┘
     ///
@@ -92,21 +89,22 @@ pub(crate) trait IDatabase {
 
 impl IDatabase for RwModel<DataBases> {
     async fn create_table(&self) -> Result<()> {
-        self.call_mut(|inner| async move { inner.create_table().await }).await
+        self.call_mut(async move |inner| inner.create_table().await).await
     }
     async fn insert_user(&self, name: String, gold: f64) -> Result<bool> {
-        self.call_mut(|mut inner| async move { inner.insert_user(&name, gold).await }).await
+        self.call_mut(async move |mut inner| inner.insert_user(&name, gold).await).await
     }
     async fn insert_user_ref_name(&self, name: &str, gold: f64) -> Result<bool> {
-        self.call_mut(|mut inner| async move { inner.insert_user(name, gold).await }).await
+        self.call_mut(async move |mut inner| inner.insert_user(name, gold).await).await
     }
 
     async fn select_all_users(&self) -> Result<Vec<User>> {
-        self.call(|inner| async move { inner.select_all_users().await }).await
+        self.call(async move |inner| inner.select_all_users().await).await
     }
 
     async fn test_unsafe_blocking(&self, name: &str, gold: f64) -> Result<bool> {
-        call_wait!(self, 3000, |_inner| async move { DB.insert_user_ref_name(name, gold).await }).await?
+        self.checked_call_mut(async move |_inner| DB.checked_call_mut(async move |mut inner| inner.insert_user(name, gold).await).await?)
+            .await?
     }
 }
 
@@ -127,7 +125,11 @@ async fn main() -> Result<()> {
         let join: JoinHandle<Result<()>> = tokio::spawn(async move {
             //each task runs 1000 times
             for j in 0..1000 {
-                DB.insert_user(i.to_string(), j as f64).await?;
+                if j % 2 == 0 {
+                    DB.insert_user(i.to_string(), j as f64).await?;
+                } else {
+                    DB.insert_user_ref_name(&i.to_string(), j as f64).await?;
+                }
             }
             Ok(())
         });
@@ -143,7 +145,8 @@ async fn main() -> Result<()> {
         println!("{:?}", user);
     }
 
-    DB.test_unsafe_blocking("123123", 1111111f64).await?;
+    let err = DB.test_unsafe_blocking("123123", 1111111f64).await.unwrap_err();
+    println!("{}", err);
 
     Ok(())
 }
@@ -1,26 +1,67 @@
-use crate::AQueue;
-
+use crate::cancel::CancelPolicy;
+use crate::error::Result;
 use crate::inner_store::InnerStore;
+use crate::runtime::{self, Runtime};
+use crate::rw_model::RefMutInner;
+use crate::state::FullPolicy;
+use crate::time::IntoDeadline;
+use crate::watch::{Subscribe, Watch};
+use crate::AQueue;
 use std::future::Future;
 use std::ops::Deref;
+use std::panic::{RefUnwindSafe, UnwindSafe};
+use std::pin::Pin;
 use std::sync::Arc;
 
 /// Actor Model
 /// Ensure Thread safety and high performance writing
-pub struct Actor<I> {
-    inner: Arc<InnerStore<I>>,
+/// the inner can be unsized, coerce `Arc<Actor<T>>` or `Box<Actor<T>>` to `Arc<Actor<dyn Trait>>`
+///
+/// the actor is Send and Sync only if the inner is Send, a !Send inner can not cross threads
+/// ```compile_fail
+/// fn share<T: Send + Sync>(_: T) {}
+/// share(aqueue::Actor::new(std::rc::Rc::new(0)));
+/// ```
+/// for a !Send inner, keep the actor in one thread by `Rc<Actor<I>>` and run the calls on a local executor,
+/// no `unsafe impl Send` needed
+/// ```
+/// use aqueue::Actor;
+/// use std::cell::RefCell;
+/// use std::rc::Rc;
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() {
+///     let actor = Rc::new(Actor::new(Rc::new(RefCell::new(Vec::new()))));
+///     let local = tokio::task::LocalSet::new();
+///     local
+///         .run_until(async move {
+///             let a_actor = actor.clone();
+///             tokio::task::spawn_local(async move { a_actor.inner_call(async move |inner| { inner.borrow_mut().push(1) }).await })
+///                 .await
+///                 .unwrap();
+///             assert_eq!(actor.inner_call(async move |inner| { inner.borrow().len() }).await, 1);
+///         })
+///         .await;
+/// }
+/// ```
+pub struct Actor<I: ?Sized> {
     queue: AQueue,
+    inner: InnerStore<I>,
 }
 
 impl<I: Default> Default for Actor<I> {
     fn default() -> Self {
         Self {
-            inner: Arc::new(InnerStore::new(Default::default())),
             queue: AQueue::new(),
+            inner: InnerStore::new(Default::default()),
         }
     }
 }
 
+/// the panic of call poison the actor
+impl<I: ?Sized> UnwindSafe for Actor<I> {}
+impl<I: ?Sized> RefUnwindSafe for Actor<I> {}
+
 pub struct RefInner<'a, T: ?Sized> {
     pub(crate) value: &'a T,
 }
@@ -37,18 +78,283 @@ impl<I: 'static> Actor<I> {
     #[inline]
     pub fn new(x: I) -> Actor<I> {
         Actor {
-            inner: Arc::new(InnerStore::new(x)),
             queue: AQueue::new(),
+            inner: InnerStore::new(x),
+        }
+    }
+
+    /// Create a actor use the runtime for timeout
+    #[inline]
+    pub fn with_runtime(x: I, runtime: impl Runtime) -> Actor<I> {
+        Actor {
+            queue: AQueue::with_runtime(runtime),
+            inner: InnerStore::new(x),
+        }
+    }
+
+    /// Create a bounded actor, at most max_pending callers wait for the actor, the running call is not counted
+    /// the later calls return [`Error::Full`](crate::Error::Full) or wait for space by the policy, with max_pending 0 the call only run if the actor is free now
+    /// # Panics
+    /// if max_pending is 0 and the policy is [`FullPolicy::Wait`](crate::FullPolicy::Wait)
+    #[inline]
+    pub fn bounded(x: I, max_pending: usize, policy: FullPolicy) -> Actor<I> {
+        Actor {
+            queue: AQueue::bounded(max_pending, policy),
+            inner: InnerStore::new(x),
+        }
+    }
+
+    /// Create a reentrant actor
+    /// call it again inside the call of this actor will run inline, other tasks are still run one by one
+    /// # Safety
+    /// the nested call get a RefMutInner of the same inner as the outer call,
+    /// the caller must make sure no `&I` or `&mut I` deref from the outer RefMutInner is alive across the nested call,
+    /// deref it again after the nested call return
+    #[inline]
+    pub unsafe fn new_reentrant(x: I) -> Actor<I> {
+        Actor {
+            queue: AQueue::new_reentrant(),
+            inner: InnerStore::new(x),
         }
     }
+}
+
+impl<I: ?Sized + 'static> Actor<I> {
+    /// a call panicked while holding the actor
+    #[inline]
+    pub fn is_poisoned(&self) -> bool {
+        self.queue.is_poisoned()
+    }
+
+    /// clear the poisoned state, make sure the inner is consistent before call it
+    #[inline]
+    pub fn clear_poison(&self) {
+        self.queue.clear_poison()
+    }
+
+    /// close the actor, the waiting and later calls return [`Error::Closed`](crate::Error::Closed), the running call is not affected
+    #[inline]
+    pub fn close(&self) {
+        self.queue.close()
+    }
+
+    /// is the actor closed
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        self.queue.is_closed()
+    }
+
+    /// the version of actor, it is changed after every finished call
+    #[inline]
+    pub fn version(&self) -> u64 {
+        self.queue.version()
+    }
+
+    /// subscribe the changes of actor, the stream wake after every finished call and yield the version
+    /// the slow consumer only get the latest version, the stream end if the actor is closed
+    #[inline]
+    pub fn subscribe(&self) -> Subscribe {
+        self.queue.subscribe()
+    }
+
+    /// watch the state of actor, the stream wake after every finished call and yield the version and the projection of the new state
+    /// the projection is run while holding the actor, the slow consumer only get the latest state, the stream end if the actor is closed or poisoned
+    #[inline]
+    pub fn watch<T: Send + 'static>(self: &Arc<Self>, project: impl Fn(&I) -> T + Send + Sync + 'static) -> Watch<T>
+    where
+        I: Send,
+    {
+        let actor = self.clone();
+        let project = Arc::new(project);
+        Watch::new(self.queue.subscribe(), move || {
+            let (actor, project) = (actor.clone(), project.clone());
+            Box::pin(async move {
+                actor
+                    .queue
+                    .checked_peek(|| (actor.version(), project(unsafe { actor.inner.get() })))
+                    .await
+            })
+        })
+    }
+
+    /// Behavior through queues,thread safe call async fn write ref mut
+    /// the ref mut is only usable while the queue is held, the async closure can not return or store it out of the call
+    /// ```compile_fail
+    /// # async fn leak(actor: &aqueue::Actor<u32>) {
+    /// let inner = actor.inner_call(async |inner| inner).await;
+    /// # }
+    /// ```
+    /// # Panics
+    /// if call it again inside the call of this actor and it is not reentrant, or the actor is closed or poisoned
+    #[inline]
+    pub async fn inner_call<R>(&self, call: impl AsyncFnOnce(RefMutInner<'_, I>) -> R) -> R {
+        self.queue
+            .run(|inner| call(unsafe { RefMutInner::from_ptr(inner.as_ptr()) }), &self.inner)
+            .await
+    }
+
+    /// Behavior through queues,thread safe call async fn write ref mut
+    /// return [`Error::Reentrant`](crate::Error::Reentrant) instead of deadlock if call it again inside the call of this actor and it is not reentrant
+    /// return [`Error::Poisoned`](crate::Error::Poisoned) if a call panicked before
+    /// return [`Error::Closed`](crate::Error::Closed) if the actor is closed
+    #[inline]
+    pub async fn checked_inner_call<R>(&self, call: impl AsyncFnOnce(RefMutInner<'_, I>) -> R) -> Result<R> {
+        self.queue
+            .checked_run(|inner| call(unsafe { RefMutInner::from_ptr(inner.as_ptr()) }), &self.inner)
+            .await
+    }
+
+    /// Thread safe call fn write ref mut
+    /// block the current thread until the call finished, for plain threads like FFI callbacks, rayon workers or Drop
+    /// # Panics
+    /// if it need wait on an async worker thread, or call it again inside the call of this actor and it is not reentrant, or the actor is closed or poisoned
+    #[inline]
+    pub fn sync_inner_call<R>(&self, call: impl FnOnce(RefMutInner<'_, I>) -> R) -> R {
+        self.checked_sync_inner_call(call).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Thread safe call fn write ref mut
+    /// block the current thread until the call finished, for plain threads like FFI callbacks, rayon workers or Drop
+    /// return [`Error::AsyncWorker`](crate::Error::AsyncWorker) if it need wait on an async worker thread, park it will stall the executor
+    /// return [`Error::Reentrant`](crate::Error::Reentrant) if call it again inside the call of this actor and it is not reentrant
+    /// return [`Error::Poisoned`](crate::Error::Poisoned) if a call panicked before
+    /// return [`Error::Closed`](crate::Error::Closed) if the actor is closed
+    #[inline]
+    pub fn checked_sync_inner_call<R>(&self, call: impl FnOnce(RefMutInner<'_, I>) -> R) -> Result<R> {
+        self.queue
+            .checked_sync_run(|inner| call(unsafe { RefMutInner::from_ptr(inner.as_ptr()) }), &self.inner)
+    }
+
+    /// Behavior through queues,thread safe call async fn write ref mut
+    /// block the current thread until the call finished, for plain threads like FFI callbacks, rayon workers or Drop
+    /// # Panics
+    /// if it is called on an async worker thread, or call it again inside the call of this actor and it is not reentrant, or the actor is closed or poisoned
+    #[inline]
+    pub fn block_inner_call<R>(&self, call: impl AsyncFnOnce(RefMutInner<'_, I>) -> R) -> R {
+        self.checked_block_inner_call(call).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Behavior through queues,thread safe call async fn write ref mut
+    /// block the current thread until the call finished, for plain threads like FFI callbacks, rayon workers or Drop
+    /// return [`Error::AsyncWorker`](crate::Error::AsyncWorker) if it is called on an async worker thread, block it will stall the executor
+    /// return [`Error::Reentrant`](crate::Error::Reentrant) if call it again inside the call of this actor and it is not reentrant
+    /// return [`Error::Poisoned`](crate::Error::Poisoned) if a call panicked before
+    /// return [`Error::Closed`](crate::Error::Closed) if the actor is closed
+    #[inline]
+    pub fn checked_block_inner_call<R>(&self, call: impl AsyncFnOnce(RefMutInner<'_, I>) -> R) -> Result<R> {
+        self.queue
+            .checked_block_run(|inner| call(unsafe { RefMutInner::from_ptr(inner.as_ptr()) }), &self.inner)
+    }
+
+    /// Behavior through queues,thread safe call async fn write ref mut, only if the actor is idle now
+    /// return [`Error::WouldBlock`](crate::Error::WouldBlock) at once if the actor is busy now, for opportunistic work
+    /// return [`Error::Reentrant`](crate::Error::Reentrant) if call it again inside the call of this actor and it is not reentrant
+    /// return [`Error::Poisoned`](crate::Error::Poisoned) if a call panicked before
+    /// return [`Error::Closed`](crate::Error::Closed) if the actor is closed
+    #[inline]
+    pub async fn try_inner_call<R>(&self, call: impl AsyncFnOnce(RefMutInner<'_, I>) -> R) -> Result<R> {
+        self.queue
+            .try_run(|inner| call(unsafe { RefMutInner::from_ptr(inner.as_ptr()) }), &self.inner)
+            .await
+    }
+
+    /// Behavior through queues,thread safe call async fn write ref mut
+    /// return [`Error::Timeout`](crate::Error::Timeout) if the call is not finished before the timeout, the waiting time is included
+    /// timeout can be a [`Duration`](std::time::Duration) or a deadline [`Instant`](std::time::Instant)
+    /// need a [`Runtime`](crate::Runtime), return [`Error::NoRuntime`](crate::Error::NoRuntime) if the queue has no runtime
+    /// Note: the call is dropped at the timeout even if it is running, the inner may be half updated,
+    /// use [`inner_call_acquire_timeout`](Self::inner_call_acquire_timeout) to only limit the waiting time
+    #[inline]
+    pub async fn inner_call_timeout<R>(&self, timeout: impl IntoDeadline, call: impl AsyncFnOnce(RefMutInner<'_, I>) -> R) -> Result<R> {
+        runtime::get(self.queue.runtime())?
+            .timeout(timeout, self.checked_inner_call(call))
+            .await?
+    }
+
+    /// Behavior through queues,thread safe call async fn write ref mut
+    /// return [`Error::Timeout`](crate::Error::Timeout) if the queue is not acquired before the timeout
+    /// once acquired the call always run to completion, the running time is not limited
+    /// timeout can be a [`Duration`](std::time::Duration) or a deadline [`Instant`](std::time::Instant)
+    /// need a [`Runtime`](crate::Runtime), return [`Error::NoRuntime`](crate::Error::NoRuntime) if the queue has no runtime
+    #[inline]
+    pub async fn inner_call_acquire_timeout<R>(&self, timeout: impl IntoDeadline, call: impl AsyncFnOnce(RefMutInner<'_, I>) -> R) -> Result<R> {
+        self.queue
+            .run_acquire_timeout(timeout, |inner| call(unsafe { RefMutInner::from_ptr(inner.as_ptr()) }), &self.inner)
+            .await
+    }
+
+    /// Behavior through queues,thread safe call async fn write ref mut
+    /// return [`Error::Cancelled`](crate::Error::Cancelled) if the cancel signal is ready before the queue is acquired,
+    /// or before the call finished if the policy is [`CancelPolicy::AlsoRunning`]
+    #[inline]
+    pub async fn inner_call_with_cancel<R>(
+        &self, cancel: impl Future, policy: CancelPolicy, call: impl AsyncFnOnce(RefMutInner<'_, I>) -> R,
+    ) -> Result<R> {
+        self.queue
+            .run_with_cancel(
+                cancel,
+                policy,
+                |inner| call(unsafe { RefMutInner::from_ptr(inner.as_ptr()) }),
+                &self.inner,
+            )
+            .await
+    }
+
+    /// Behavior through queues,thread safe call async fn write ref mut
+    /// the call run to completion even if the caller is dropped, the inner never be half updated by a dropped caller
+    /// once the actor is acquired, the call is spawned on the runtime, the result is discarded if the caller is dropped
+    /// return [`Error::Poisoned`](crate::Error::Poisoned) if a call panicked before
+    /// the panic of this call poison the model and is resumed on the caller
+    /// return [`Error::Cancelled`](crate::Error::Cancelled) if the runtime drop the call without run, like at shutdown
+    /// need a [`Runtime`](crate::Runtime), return [`Error::NoRuntime`](crate::Error::NoRuntime) if the actor has no runtime
+    /// the call return a boxed future like `|inner| Box::pin(async move { .. })`, the ref mut can not escape the call
+    #[inline]
+    pub async fn inner_call_detached<R>(
+        self: &Arc<Self>, call: impl for<'g> FnOnce(RefMutInner<'g, I>) -> Pin<Box<dyn Future<Output = R> + Send + 'g>>,
+    ) -> Result<R>
+    where
+        I: Send,
+        R: Send + 'static,
+    {
+        let actor = self.clone();
+        self.queue
+            .run_detached(
+                move |inner: &InnerStore<I>| {
+                    // the actor is kept alive by the spawned call
+                    let future = call(unsafe { RefMutInner::from_ptr(inner.as_ptr()) });
+                    async move {
+                        let _actor = actor;
+                        future.await
+                    }
+                },
+                &self.inner,
+            )
+            .await
+    }
 
-    /// Behavior through queues,thread safe call async fn
+    /// Behavior through queues,thread safe call fn write ref mut on the blocking thread pool
+    /// for the heavy sync work like compression or big sorts, not stall the async worker
+    /// the call run on the blocking thread pool of the runtime once the actor is acquired, the actor is released when the call return
+    /// the call run to completion even if the caller is dropped, the result is discarded
+    /// return [`Error::Reentrant`](crate::Error::Reentrant) instead of deadlock if call it again inside the call of this actor and it is not reentrant
+    /// return [`Error::Poisoned`](crate::Error::Poisoned) if a call panicked before
+    /// the panic of this call poison the model and is resumed on the caller
+    /// return [`Error::Cancelled`](crate::Error::Cancelled) if the runtime drop the call without run, like at shutdown
+    /// return [`Error::Closed`](crate::Error::Closed) if the actor is closed
+    /// need a [`Runtime`](crate::Runtime), return [`Error::NoRuntime`](crate::Error::NoRuntime) if the actor has no runtime
     #[inline]
-    pub async fn inner_call<T, R>(&self, call: impl FnOnce(Arc<InnerStore<I>>) -> T) -> R
+    pub async fn inner_call_blocking<R>(self: &Arc<Self>, call: impl FnOnce(RefMutInner<'_, I>) -> R + Send + 'static) -> Result<R>
     where
-        T: Future<Output = R>,
+        I: Send + 'static,
+        R: Send + 'static,
     {
-        self.queue.run(call, self.inner.clone()).await
+        // the actor is kept alive by the blocking call
+        self.queue
+            .run_blocking(
+                move |actor: Arc<Self>| call(unsafe { RefMutInner::from_ptr(actor.inner.as_ptr()) }),
+                self.clone(),
+            )
+            .await
     }
 
     /// # Safety
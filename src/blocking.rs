@@ -0,0 +1,98 @@
+use crate::error::{Error, Result};
+use crate::holder;
+use std::cell::Cell;
+use std::future::Future;
+use std::hint::spin_loop;
+use std::pin::pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+
+/// spin count before park the thread
+const SPIN_LIMIT: u32 = 64;
+
+thread_local! {
+    /// the thread is allowed to block, in block_on of aqueue
+    static BLOCKING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// spin briefly, then park the thread and wait in the same queue as the async waiters
+/// return [`Error::AsyncWorker`] instead of park an async worker thread, the holder may never be polled again
+#[inline]
+pub(crate) fn acquire<G>(mut try_acquire: impl FnMut() -> Option<G>, block: impl FnOnce() -> G) -> Result<G> {
+    for _ in 0..SPIN_LIMIT {
+        if let Some(guard) = try_acquire() {
+            return Ok(guard);
+        }
+        spin_loop();
+    }
+    if in_async_worker() {
+        return Err(Error::AsyncWorker);
+    }
+    Ok(block())
+}
+
+/// is the current thread an async worker, block it will stall the executor
+/// the blocking pool threads of the queue calls are marked by [`allow`], the other threads in a tokio runtime context are workers
+#[inline]
+pub(crate) fn in_async_worker() -> bool {
+    if BLOCKING.with(Cell::get) {
+        return false;
+    }
+    if holder::is_polled() {
+        return true;
+    }
+    #[cfg(feature = "tokio_runtime")]
+    if tokio::runtime::Handle::try_current().is_ok() {
+        return true;
+    }
+    #[cfg(feature = "async_std_runtime")]
+    if async_std::task::try_current().is_some() {
+        return true;
+    }
+    false
+}
+
+/// restore the blocking flag when leave, even if panic
+struct Restore(bool);
+
+impl Drop for Restore {
+    #[inline]
+    fn drop(&mut self) {
+        BLOCKING.with(|blocking| blocking.set(self.0));
+    }
+}
+
+/// run f as a thread allowed to block
+#[inline]
+pub(crate) fn allow<R>(f: impl FnOnce() -> R) -> R {
+    let _restore = Restore(BLOCKING.with(|blocking| blocking.replace(true)));
+    f()
+}
+
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    #[inline]
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+
+    #[inline]
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// block the current thread until the future is ready
+pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    allow(|| loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    })
+}
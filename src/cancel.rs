@@ -0,0 +1,45 @@
+use crate::error::{Error, Result};
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// when the cancel signal abort the call
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CancelPolicy {
+    /// abort only while waiting the queue, once acquired the call always run to completion
+    #[default]
+    OnlyWaiting,
+    /// abort while waiting and the running call, the inner may be half updated
+    AlsoRunning,
+}
+
+pin_project_lite::pin_project! {
+    /// return [`Error::Cancelled`] if the cancel signal is ready before the future
+    pub(crate) struct Cancellable<F, C> {
+        #[pin]
+        future: F,
+        #[pin]
+        cancel: C,
+    }
+}
+
+impl<F, C> Cancellable<F, C> {
+    #[inline]
+    pub(crate) fn new(future: F, cancel: C) -> Self {
+        Cancellable { future, cancel }
+    }
+}
+
+impl<F: Future, C: Future> Future for Cancellable<F, C> {
+    type Output = Result<F::Output>;
+
+    #[inline]
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        // the cancelled call leave the queue even if it is ready
+        if this.cancel.poll(cx).is_ready() {
+            return Poll::Ready(Err(Error::Cancelled));
+        }
+        this.future.poll(cx).map(Ok)
+    }
+}
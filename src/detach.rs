@@ -0,0 +1,95 @@
+use crate::blocking;
+use crate::error::{Error, Result};
+use crate::oneshot;
+use crate::runtime::Runtime;
+use crate::state::State;
+use std::any::Any;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// the output of the spawned call, or its panic payload
+type Output<R> = std::result::Result<R, Box<dyn Any + Send>>;
+
+pin_project_lite::pin_project! {
+    /// catch the panic of future, the locals of the future are dropped while panicking, so the poison guard inside see it
+    struct CatchUnwind<F> {
+        #[pin]
+        future: F,
+    }
+}
+
+impl<F: Future> Future for CatchUnwind<F> {
+    type Output = Output<F::Output>;
+
+    #[inline]
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let future = self.project().future;
+        match panic::catch_unwind(AssertUnwindSafe(|| future.poll(cx))) {
+            Ok(poll) => poll.map(Ok),
+            Err(payload) => Poll::Ready(Err(payload)),
+        }
+    }
+}
+
+/// the caller get the output, resume the panic of the call like the inline calls,
+/// return [`Error::Cancelled`] if the call is dropped by the runtime without run, like at shutdown
+#[inline]
+async fn recv<R>(rx: oneshot::Receiver<Output<R>>) -> Result<R> {
+    match rx.await {
+        Some(Ok(output)) => Ok(output),
+        Some(Err(payload)) => panic::resume_unwind(payload),
+        None => Err(Error::Cancelled),
+    }
+}
+
+/// spawn the future holding the guard of queue, send the output back to the caller
+/// the guard is released before the caller is woken up
+/// with the state of a write call, the panic poison the queue before it is resumed on the caller,
+/// without it, like the read call, the panic is only resumed on the caller
+#[inline]
+pub(crate) async fn spawn<G, R>(
+    runtime: &dyn Runtime, guard: G, state: Option<Arc<State>>, future: impl Future<Output = R> + Send + 'static,
+) -> Result<R>
+where
+    G: Send + 'static,
+    R: Send + 'static,
+{
+    let (tx, rx) = oneshot::channel();
+    runtime.spawn(Box::pin(async move {
+        let output = CatchUnwind {
+            future: async move {
+                let _guard = guard;
+                let _poison = state.as_ref().map(|state| state.poison_guard());
+                future.await
+            },
+        }
+        .await;
+        tx.send(output);
+    }));
+    recv(rx).await
+}
+
+/// run the call on the blocking thread pool holding the guard of queue, send the output back to the caller
+/// the guard is released and the panic is handled like [`spawn`]
+#[inline]
+pub(crate) async fn spawn_blocking<G, R>(
+    runtime: &dyn Runtime, guard: G, state: Option<Arc<State>>, call: impl FnOnce() -> R + Send + 'static,
+) -> Result<R>
+where
+    G: Send + 'static,
+    R: Send + 'static,
+{
+    let (tx, rx) = oneshot::channel();
+    runtime.spawn_blocking(Box::new(move || {
+        let output = panic::catch_unwind(AssertUnwindSafe(|| {
+            let _guard = guard;
+            let _poison = state.as_ref().map(|state| state.poison_guard());
+            blocking::allow(call)
+        }));
+        tx.send(output);
+    }));
+    recv(rx).await
+}
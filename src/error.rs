@@ -0,0 +1,52 @@
+use std::fmt;
+
+/// aqueue error
+/// returned by the checked, timeout, cancel and try calls of queues and models
+/// more variants may be added later, match it with a wildcard arm
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// the call did not finish in time
+    Timeout,
+    /// the queue is closed, no more call will run
+    Closed,
+    /// a call panicked while holding the queue, the inner may be half updated
+    Poisoned,
+    /// the queue reached its max pending callers
+    Full,
+    /// the call is cancelled, or the detached call is dropped by the runtime without run, like at shutdown
+    Cancelled,
+    /// the queue is busy now, the call need to wait
+    WouldBlock,
+    /// the queue is already held by the current task, wait it will deadlock
+    Reentrant,
+    /// the blocking call is on an async worker thread, block it will stall the executor
+    /// the worker is known inside a queue call polled by the executor, in a tokio runtime context or in an async-std task,
+    /// the blocking pool threads of the `*_blocking` calls are not async workers, a plain tokio spawn_blocking thread can not be told from a worker
+    AsyncWorker,
+    /// the timeout, detached or blocking call need a runtime, but the queue has no runtime
+    NoRuntime,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Timeout => f.write_str("queue call timed out"),
+            Error::Closed => f.write_str("queue is closed"),
+            Error::Poisoned => f.write_str("queue is poisoned by a panicked call"),
+            Error::Full => f.write_str("queue is full"),
+            Error::Cancelled => f.write_str("queue call is cancelled"),
+            Error::WouldBlock => f.write_str("queue is busy, call would block"),
+            Error::Reentrant => f.write_str("queue is already held by the current task, nested call would deadlock"),
+            Error::AsyncWorker => f.write_str("blocking queue call on an async worker thread would stall the executor"),
+            Error::NoRuntime => {
+                f.write_str("queue has no runtime, enable feature \"tokio_runtime\", \"async_std_runtime\" or \"smol_runtime\", or use with_runtime")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// aqueue result
+pub type Result<T, E = Error> = std::result::Result<T, E>;
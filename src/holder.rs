@@ -0,0 +1,108 @@
+use std::cell::Cell;
+use std::future::Future;
+use std::pin::Pin;
+use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::task::{Context, Poll};
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(1);
+
+thread_local! {
+    /// top of the queue id stack held by the task currently being polled on this thread
+    static TOP: Cell<*const Node> = const { Cell::new(ptr::null()) };
+}
+
+struct Node {
+    id: usize,
+    /// this or a lower holder is polled by an async executor
+    polled: bool,
+    prev: *const Node,
+}
+
+/// restore the stack top when leave the scope, even if panic
+struct Restore<'a>(&'a Cell<*const Node>, *const Node);
+
+impl Drop for Restore<'_> {
+    #[inline]
+    fn drop(&mut self) {
+        self.0.set(self.1);
+    }
+}
+
+/// alloc a new queue id
+#[inline]
+pub(crate) fn next_id() -> usize {
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// is the queue held by the current task
+#[inline]
+pub(crate) fn is_held(id: usize) -> bool {
+    let mut node = TOP.with(|top| top.get());
+    while !node.is_null() {
+        // Safety: every node lives on the stack of a `push` call below us
+        let current = unsafe { &*node };
+        if current.id == id {
+            return true;
+        }
+        node = current.prev;
+    }
+    false
+}
+
+/// is a queue call polled by an async executor on this thread
+#[inline]
+pub(crate) fn is_polled() -> bool {
+    let node = TOP.with(|top| top.get());
+    // Safety: every node lives on the stack of a `push` call below us
+    !node.is_null() && unsafe { (*node).polled }
+}
+
+/// run f as the holder of queue
+#[inline]
+pub(crate) fn scope<R>(id: usize, f: impl FnOnce() -> R) -> R {
+    push(id, false, f)
+}
+
+/// push the holder node in one access of the thread local, run f and pop it
+#[inline]
+fn push<R>(id: usize, polled: bool, f: impl FnOnce() -> R) -> R {
+    TOP.with(|top| {
+        let prev = top.get();
+        // Safety: every node lives on the stack of a `push` call below us
+        let polled = polled || (!prev.is_null() && unsafe { (*prev).polled });
+        let node = Node { id, polled, prev };
+        let _restore = Restore(top, prev);
+        top.set(&node);
+        f()
+    })
+}
+
+pin_project_lite::pin_project! {
+    /// run the future as the holder of queue
+    pub(crate) struct Holding<F> {
+        id: usize,
+        #[pin]
+        future: F,
+    }
+}
+
+/// create the call future and poll it as the holder of queue
+#[inline]
+pub(crate) fn hold<A, T: Future>(id: usize, call: impl FnOnce(A) -> T, arg: A) -> Holding<T> {
+    Holding {
+        id,
+        future: scope(id, || call(arg)),
+    }
+}
+
+impl<F: Future> Future for Holding<F> {
+    type Output = F::Output;
+
+    #[inline]
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let future = this.future;
+        push(*this.id, true, || future.poll(cx))
+    }
+}
@@ -1,24 +1,33 @@
 use std::cell::UnsafeCell;
+use std::ptr::NonNull;
 
 /// Inner impl
 /// # Safety
 /// This is Thread Unsafe,Please do not use it at will.
-pub struct InnerStore<T>(UnsafeCell<T>);
-unsafe impl<T> Sync for InnerStore<T> {}
-unsafe impl<T> Send for InnerStore<T> {}
+/// it is only accessed while holding the queue like a mutex, so Send and Sync need T is Send
+pub(crate) struct InnerStore<T: ?Sized>(UnsafeCell<T>);
+unsafe impl<T: ?Sized + Send> Sync for InnerStore<T> {}
 
 impl<T> InnerStore<T> {
     #[inline]
     pub(crate) fn new(x: T) -> InnerStore<T> {
         InnerStore(UnsafeCell::new(x))
     }
+}
+
+impl<T: ?Sized> InnerStore<T> {
+    /// the pointer of inner, no ref is created on the way
+    /// the nested call of reentrant actor get its own pointer without invalidate the outer one
     #[inline]
-    #[allow(clippy::mut_from_ref)]
-    pub fn get_mut(&self) -> &mut T {
-        unsafe { &mut *self.0.get() }
+    pub(crate) fn as_ptr(&self) -> NonNull<T> {
+        // Safety: the pointer of UnsafeCell is never null
+        unsafe { NonNull::new_unchecked(self.0.get()) }
     }
+
+    /// # Safety
+    /// The caller must make sure no one is writing at the same time
     #[inline]
-    pub fn get(&self) -> &T {
-        unsafe { &*self.0.get() }
+    pub(crate) unsafe fn get(&self) -> &T {
+        &*self.0.get()
     }
 }
@@ -1,134 +1,95 @@
 mod actor;
+mod blocking;
+mod cancel;
+mod detach;
+mod error;
+mod holder;
 mod inner_store;
 mod mutex;
+mod oneshot;
 mod pc_model;
+mod runtime;
 mod rw_model;
 mod rwlock;
 mod semaphore;
+mod snapshot_model;
+mod state;
+mod time;
+mod watch;
 
 pub use actor::Actor;
-pub use mutex::AQueue;
+pub use cancel::CancelPolicy;
+pub use error::{Error, Result};
+pub use mutex::{AQueue, AQueueGuard, AQueueGuardArc};
 pub use pc_model::PCModel;
-pub use rw_model::RwModel;
-pub use rwlock::RwQueue;
-pub use semaphore::SemaphoreQueue;
+#[cfg(feature = "async_std_runtime")]
+pub use runtime::AsyncStdRuntime;
+#[cfg(feature = "smol_runtime")]
+pub use runtime::SmolRuntime;
+#[cfg(feature = "tokio_runtime")]
+pub use runtime::TokioRuntime;
+pub use runtime::{default_runtime, BoxFuture, Runtime};
+pub use rw_model::{RwModel, RwModelReadGuard, RwModelWriteGuard};
+pub use rwlock::{RwPolicy, RwQueue, RwQueueReadGuard, RwQueueReadGuardArc, RwQueueWriteGuard, RwQueueWriteGuardArc};
+pub use semaphore::{SemaphoreQueue, SemaphoreQueueGuard, SemaphoreQueueGuardArc};
+pub use snapshot_model::SnapshotModel;
+pub use state::FullPolicy;
+pub use time::{IntoDeadline, Timeout};
+pub use watch::{Subscribe, Watch};
 
 /// inner call wait ms throw time error
-/// need on feature "tokio_time" or "async_std_time"
-/// # tokio runtime:
+/// prefer [`Actor::inner_call_timeout`], it take a Duration or Instant
+/// need a [`Runtime`], enable feature "tokio_runtime", "async_std_runtime" or "smol_runtime"
 /// ``` toml
-/// aqueue = { version = "^1.2.10", features = ["tokio_time"] }
+/// aqueue = { version = "^1.2.10", features = ["tokio_runtime"] }
 /// ```
 /// # Example
 /// ``` ignore
 ///     async fn test_unsafe_blocking(&self, name: String, gold: f64) -> Result<bool> {
-///         inner_wait!(self, 30000, |_inner| async move { DB.insert_user(name, gold).await }).await?
+///         inner_wait!(self, 30000, async move |_inner| { DB.insert_user(name, gold).await }).await?
 ///     }
 /// ```
-#[cfg(all(feature = "tokio_time", not(feature = "async_std_time")))]
 #[macro_export]
 macro_rules! inner_wait {
     ($actor:expr,$timeout:expr,$fun:expr) => {
-        tokio::time::timeout(std::time::Duration::from_millis($timeout), $actor.inner_call($fun))
-    };
-}
-
-/// inner call wait ms throw time error
-/// need on feature "tokio_time" or "async_std_time"
-/// # async_std runtime:
-/// ``` toml
-/// aqueue = { version = "^1.2.10", features = ["async_std_time"] }
-/// ```
-/// # Example
-/// ``` ignore
-///     async fn test_unsafe_blocking(&self, name: String, gold: f64) -> Result<bool> {
-///         inner_wait!(self, 30000, |_inner| async move { DB.insert_user(name, gold).await }).await?
-///     }
-/// ```
-#[cfg(all(feature = "async_std_time", not(feature = "tokio_time")))]
-#[macro_export]
-macro_rules! inner_wait {
-    ($actor:expr,$timeout:expr,$fun:expr) => {
-        async_std::future::timeout(std::time::Duration::from_millis($timeout), $actor.inner_call($fun))
-    };
-}
-
-/// call_mut wait ms throw time error
-/// need on feature "tokio_time" or "async_std_time"
-/// # tokio runtime:
-/// ``` toml
-/// aqueue = { version = "^1.3.2", features = ["tokio_time"] }
-/// ```
-/// # Example
-/// ``` ignore
-///     async fn test_unsafe_blocking(&self, name: String, gold: f64) -> Result<bool> {
-///         call_mut_wait!(self, 30000, |_inner| async move { DB.insert_user(name, gold).await }).await?
-///     }
-/// ```
-#[cfg(all(feature = "tokio_time", not(feature = "async_std_time")))]
-#[macro_export]
-macro_rules! call_mut_wait {
-    ($model:expr,$timeout:expr,$fun:expr) => {
-        tokio::time::timeout(std::time::Duration::from_millis($timeout), $model.call_mut($fun))
+        $actor.inner_call_timeout(std::time::Duration::from_millis($timeout), $fun)
     };
 }
 
 /// call_mut wait ms throw time error
-/// need on feature "tokio_time" or "async_std_time"
-/// # tokio runtime:
+/// prefer [`RwModel::call_mut_timeout`], it take a Duration or Instant
+/// need a [`Runtime`], enable feature "tokio_runtime", "async_std_runtime" or "smol_runtime"
 /// ``` toml
-/// aqueue = { version = "^1.3.2", features = ["async_std_time"] }
+/// aqueue = { version = "^1.3.2", features = ["tokio_runtime"] }
 /// ```
 /// # Example
 /// ``` ignore
 ///     async fn test_unsafe_blocking(&self, name: String, gold: f64) -> Result<bool> {
-///         call_mut_wait!(self, 30000, |_inner| async move { DB.insert_user(name, gold).await }).await?
+///         call_mut_wait!(self, 30000, async move |_inner| { DB.insert_user(name, gold).await }).await?
 ///     }
 /// ```
-#[cfg(all(feature = "async_std_time", not(feature = "tokio_time")))]
 #[macro_export]
 macro_rules! call_mut_wait {
     ($model:expr,$timeout:expr,$fun:expr) => {
-        async_std::future::timeout(std::time::Duration::from_millis($timeout), $model.call_mut($fun))
-    };
-}
-
-/// call wait ms throw time error
-/// need on feature "tokio_time" or "async_std_time"
-/// # tokio runtime:
-/// ``` toml
-/// aqueue = { version = "^1.3.2", features = ["tokio_time"] }
-/// ```
-/// # Example
-/// ``` ignore
-///     async fn test_unsafe_blocking(&self, name: String, gold: f64) -> Result<bool> {
-///         call_wait!(self, 30000, |_inner| async move { DB.insert_user(name, gold).await }).await?
-///     }
-/// ```
-#[cfg(all(feature = "tokio_time", not(feature = "async_std_time")))]
-#[macro_export]
-macro_rules! call_wait {
-    ($model:expr,$timeout:expr,$fun:expr) => {
-        tokio::time::timeout(std::time::Duration::from_millis($timeout), $model.call($fun))
+        $model.call_mut_timeout(std::time::Duration::from_millis($timeout), $fun)
     };
 }
 
 /// call wait ms throw time error
-/// need on feature "tokio_time" or "async_std_time"
-/// # tokio runtime:
+/// prefer [`RwModel::call_timeout`], it take a Duration or Instant
+/// need a [`Runtime`], enable feature "tokio_runtime", "async_std_runtime" or "smol_runtime"
 /// ``` toml
-/// aqueue = { version = "^1.3.2", features = ["async_std_time"] }
+/// aqueue = { version = "^1.3.2", features = ["tokio_runtime"] }
 /// ```
 /// # Example
 /// ``` ignore
 ///     async fn test_unsafe_blocking(&self, name: String, gold: f64) -> Result<bool> {
-///         call_wait!(self, 30000, |_inner| async move { DB.insert_user(name, gold).await }).await?
+///         call_wait!(self, 30000, async move |_inner| { DB.insert_user(name, gold).await }).await?
 ///     }
 /// ```
-#[cfg(all(feature = "async_std_time", not(feature = "tokio_time")))]
 #[macro_export]
 macro_rules! call_wait {
     ($model:expr,$timeout:expr,$fun:expr) => {
-        async_std::future::timeout(std::time::Duration::from_millis($timeout), $model.call($fun))
+        $model.call_timeout(std::time::Duration::from_millis($timeout), $fun)
     };
 }
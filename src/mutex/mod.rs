@@ -1,47 +1,435 @@
-use async_lock::Mutex;
+use crate::blocking;
+use crate::cancel::{CancelPolicy, Cancellable};
+use crate::detach;
+use crate::error::{Error, Result};
+use crate::holder;
+use crate::runtime::{self, Runtime};
+use crate::state::{FullPolicy, PoisonGuard, State};
+use crate::time::IntoDeadline;
+use crate::watch::Subscribe;
+use async_lock::{Mutex, MutexGuard, MutexGuardArc};
 use std::future::Future;
-use std::hint::spin_loop;
+use std::panic::{RefUnwindSafe, UnwindSafe};
+use std::pin::pin;
+use std::sync::Arc;
 
 /// async future thread safe mutex
 pub struct AQueue {
-    lock: Mutex<()>,
+    id: usize,
+    reentrant: bool,
+    state: Arc<State>,
+    runtime: Option<Arc<dyn Runtime>>,
+    lock: Arc<Mutex<()>>,
 }
 
 impl Default for AQueue {
     #[inline]
     fn default() -> Self {
-        AQueue { lock: Mutex::new(()) }
+        AQueue {
+            id: holder::next_id(),
+            reentrant: false,
+            state: Arc::default(),
+            runtime: None,
+            lock: Arc::new(Mutex::new(())),
+        }
     }
 }
 
+/// the panic of call poison the queue
+impl UnwindSafe for AQueue {}
+impl RefUnwindSafe for AQueue {}
+
 impl AQueue {
     #[inline]
     pub fn new() -> AQueue {
         AQueue::default()
     }
 
+    /// Create a reentrant queue
+    /// the task holding the queue run nested call inline, other tasks are still run one by one
+    #[inline]
+    pub fn new_reentrant() -> AQueue {
+        AQueue {
+            reentrant: true,
+            ..AQueue::default()
+        }
+    }
+
+    /// Create a queue use the runtime for timeout
+    #[inline]
+    pub fn with_runtime(runtime: impl Runtime) -> AQueue {
+        AQueue {
+            runtime: Some(Arc::new(runtime)),
+            ..AQueue::default()
+        }
+    }
+
+    /// Create a bounded queue, at most max_pending callers wait for the lock, the holder of the lock is not counted
+    /// the later calls return [`Error::Full`] or wait for space by the policy, with max_pending 0 the call only run if the lock is free now
+    /// # Panics
+    /// if max_pending is 0 and the policy is [`FullPolicy::Wait`](crate::FullPolicy::Wait)
+    #[inline]
+    pub fn bounded(max_pending: usize, policy: FullPolicy) -> AQueue {
+        AQueue {
+            state: Arc::new(State::bounded(max_pending, policy)),
+            ..AQueue::default()
+        }
+    }
+
+    /// Create a reentrant queue use the runtime for timeout
+    #[inline]
+    pub fn new_reentrant_with_runtime(runtime: impl Runtime) -> AQueue {
+        AQueue {
+            reentrant: true,
+            runtime: Some(Arc::new(runtime)),
+            ..AQueue::default()
+        }
+    }
+
+    /// is reentrant queue
+    #[inline]
+    pub fn is_reentrant(&self) -> bool {
+        self.reentrant
+    }
+
+    /// the runtime of timeout, set by with_runtime or the default runtime of the enabled features
+    #[inline]
+    pub fn runtime(&self) -> Option<&dyn Runtime> {
+        runtime::or_default(&self.runtime)
+    }
+
+    /// close the queue, the waiting and later calls return [`Error::Closed`], the running call is not affected
+    #[inline]
+    pub fn close(&self) {
+        self.state.close()
+    }
+
+    /// is the queue closed
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        self.state.is_closed()
+    }
+
+    /// a call panicked while holding the queue
+    #[inline]
+    pub fn is_poisoned(&self) -> bool {
+        self.state.is_poisoned()
+    }
+
+    /// clear the poisoned state, make sure the state is consistent before call it
+    #[inline]
+    pub fn clear_poison(&self) {
+        self.state.clear_poison()
+    }
+
+    /// the count of the finished write calls
+    #[inline]
+    pub(crate) fn version(&self) -> u64 {
+        self.state.version()
+    }
+
+    /// stream of the version, changed after every finished write call
+    #[inline]
+    pub(crate) fn subscribe(&self) -> Subscribe {
+        Subscribe::new(self.state.clone())
+    }
+
+    /// wait the lock, the caller is pending until the lock is acquired
+    #[inline]
+    async fn acquire(&self) -> Result<MutexGuard<'_, ()>> {
+        self.state.enter(|| self.lock.try_lock(), || self.lock.lock()).await
+    }
+
+    /// run fn while holding the lock, it is not a write call, not poison or change the version
+    /// return [`Error::Reentrant`] instead of deadlock if the current task is already holding this queue
+    #[inline]
+    pub(crate) async fn checked_peek<R>(&self, call: impl FnOnce() -> R) -> Result<R> {
+        if holder::is_held(self.id) {
+            return Err(Error::Reentrant);
+        }
+        let _guard = self.acquire().await?;
+        Ok(call())
+    }
+
+    /// wait the owned lock, the caller is pending until the lock is acquired
+    #[inline]
+    async fn acquire_arc(&self) -> Result<MutexGuardArc<()>> {
+        self.state.enter(|| self.lock.try_lock_arc(), || self.lock.lock_arc()).await
+    }
+
     /// Sync run fn
-    /// Note: it is not based on fair lock. It will never be called when the mutex has unprocessed
+    /// spin briefly, then park the thread and wait in first in, first run order with the async callers
+    /// # Panics
+    /// if it need wait on an async worker thread, or the current task is already holding this queue and it is not reentrant, or the queue is closed or poisoned
     #[inline]
     pub fn sync_run<A, R>(&self, call: impl FnOnce(A) -> R, arg: A) -> R {
-        loop {
-            let guard = self.lock.try_lock();
-            if guard.is_some() {
-                return call(arg);
-            } else {
-                spin_loop();
-            }
+        self.checked_sync_run(call, arg).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Sync run fn
+    /// return [`Error::AsyncWorker`] if it need wait on an async worker thread, park it will stall the executor and the holder may never release the queue
+    /// return [`Error::Reentrant`] if the current task is already holding this queue and it is not reentrant
+    /// return [`Error::Poisoned`] if a call panicked before, the panic of call poison the queue
+    /// return [`Error::Closed`] if the queue is closed
+    #[inline]
+    pub fn checked_sync_run<A, R>(&self, call: impl FnOnce(A) -> R, arg: A) -> Result<R> {
+        if holder::is_held(self.id) {
+            return if self.reentrant { Ok(call(arg)) } else { Err(Error::Reentrant) };
         }
+        let _guard = self.state.sync_enter(
+            || self.lock.try_lock(),
+            || blocking::acquire(|| self.lock.try_lock(), || self.lock.lock_blocking()),
+        )?;
+        let _poison = self.state.poison_guard();
+        Ok(holder::scope(self.id, || call(arg)))
     }
 
     /// Async lock run fn
     /// The greatest truths are the simplest
+    /// # Panics
+    /// if the current task is already holding this queue and it is not reentrant, or the queue is closed or poisoned
     #[inline]
     pub async fn run<A, T, R>(&self, call: impl FnOnce(A) -> T, arg: A) -> R
     where
         T: Future<Output = R>,
     {
-        let _guard = self.lock.lock().await;
-        call(arg).await
+        self.checked_run(call, arg).await.unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Async lock run fn
+    /// return [`Error::Reentrant`] instead of deadlock if the current task is already holding this queue and it is not reentrant
+    /// return [`Error::Poisoned`] if a call panicked before, the panic of call poison the queue
+    /// return [`Error::Closed`] if the queue is closed
+    #[inline]
+    pub async fn checked_run<A, T, R>(&self, call: impl FnOnce(A) -> T, arg: A) -> Result<R>
+    where
+        T: Future<Output = R>,
+    {
+        if holder::is_held(self.id) {
+            return if self.reentrant { Ok(call(arg).await) } else { Err(Error::Reentrant) };
+        }
+        let _guard = self.acquire().await?;
+        let _poison = self.state.poison_guard();
+        Ok(holder::hold(self.id, call, arg).await)
     }
+
+    /// Blocking run async fn
+    /// block the current thread until the call finished, the async fn is run on the current thread
+    /// it wait in first in, first run order with the async callers, for plain threads like FFI callbacks, rayon workers or Drop
+    /// # Panics
+    /// if it is called on an async worker thread, or the current task is already holding this queue and it is not reentrant, or the queue is closed or poisoned
+    #[inline]
+    pub fn block_run<A, T, R>(&self, call: impl FnOnce(A) -> T, arg: A) -> R
+    where
+        T: Future<Output = R>,
+    {
+        self.checked_block_run(call, arg).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Blocking run async fn
+    /// block the current thread until the call finished, the async fn is run on the current thread
+    /// it wait in first in, first run order with the async callers, for plain threads like FFI callbacks, rayon workers or Drop
+    /// return [`Error::AsyncWorker`] if it is called on an async worker thread, block it will stall the executor
+    /// return [`Error::Reentrant`] if the current task is already holding this queue and it is not reentrant
+    /// return [`Error::Poisoned`] if a call panicked before, the panic of call poison the queue
+    /// return [`Error::Closed`] if the queue is closed
+    #[inline]
+    pub fn checked_block_run<A, T, R>(&self, call: impl FnOnce(A) -> T, arg: A) -> Result<R>
+    where
+        T: Future<Output = R>,
+    {
+        if blocking::in_async_worker() {
+            return Err(Error::AsyncWorker);
+        }
+        blocking::block_on(self.checked_run(call, arg))
+    }
+
+    /// Async lock run fn, only if the lock is free now
+    /// return [`Error::WouldBlock`] at once if the lock is held by others
+    /// return [`Error::Reentrant`] if the current task is already holding this queue and it is not reentrant
+    /// return [`Error::Poisoned`] if a call panicked before, the panic of call poison the queue
+    /// return [`Error::Closed`] if the queue is closed
+    #[inline]
+    pub async fn try_run<A, T, R>(&self, call: impl FnOnce(A) -> T, arg: A) -> Result<R>
+    where
+        T: Future<Output = R>,
+    {
+        if holder::is_held(self.id) {
+            return if self.reentrant { Ok(call(arg).await) } else { Err(Error::Reentrant) };
+        }
+        self.state.check_closed()?;
+        let _guard = self.lock.try_lock().ok_or(Error::WouldBlock)?;
+        self.state.check()?;
+        let _poison = self.state.poison_guard();
+        Ok(holder::hold(self.id, call, arg).await)
+    }
+
+    /// Async lock run fn
+    /// return [`Error::Timeout`](crate::Error::Timeout) if the lock is not acquired before the timeout
+    /// once acquired the call always run to completion, the running time is not limited
+    /// timeout can be a [`Duration`](std::time::Duration) or a deadline [`Instant`](std::time::Instant)
+    /// need a [`Runtime`](crate::Runtime), return [`Error::NoRuntime`](crate::Error::NoRuntime) if the queue has no runtime
+    #[inline]
+    pub async fn run_acquire_timeout<A, T, R>(&self, timeout: impl IntoDeadline, call: impl FnOnce(A) -> T, arg: A) -> Result<R>
+    where
+        T: Future<Output = R>,
+    {
+        if holder::is_held(self.id) {
+            return if self.reentrant { Ok(call(arg).await) } else { Err(Error::Reentrant) };
+        }
+        let _guard = runtime::get(self.runtime())?.timeout(timeout, self.acquire()).await??;
+        let _poison = self.state.poison_guard();
+        Ok(holder::hold(self.id, call, arg).await)
+    }
+
+    /// Async lock run fn
+    /// return [`Error::Cancelled`](crate::Error::Cancelled) if the cancel signal is ready before the lock is acquired,
+    /// or before the call finished if the policy is [`CancelPolicy::AlsoRunning`]
+    /// cancel can be any future, example `token.cancelled()` of tokio-util CancellationToken
+    #[inline]
+    pub async fn run_with_cancel<A, T, R>(&self, cancel: impl Future, policy: CancelPolicy, call: impl FnOnce(A) -> T, arg: A) -> Result<R>
+    where
+        T: Future<Output = R>,
+    {
+        let mut cancel = pin!(cancel);
+        if holder::is_held(self.id) {
+            return match (self.reentrant, policy) {
+                (false, _) => Err(Error::Reentrant),
+                (true, CancelPolicy::OnlyWaiting) => Ok(call(arg).await),
+                (true, CancelPolicy::AlsoRunning) => Cancellable::new(call(arg), cancel).await,
+            };
+        }
+        let _guard = Cancellable::new(self.acquire(), cancel.as_mut()).await??;
+        let _poison = self.state.poison_guard();
+        let call = holder::hold(self.id, call, arg);
+        match policy {
+            CancelPolicy::OnlyWaiting => Ok(call.await),
+            CancelPolicy::AlsoRunning => Cancellable::new(call, cancel).await,
+        }
+    }
+
+    /// Async lock run fn, the call run to completion even if the caller is dropped
+    /// once the lock is acquired, the call is spawned on the runtime, the result is discarded if the caller is dropped
+    /// return [`Error::Reentrant`] instead of deadlock if the current task is already holding this queue and it is not reentrant,
+    /// the nested call of reentrant queue run inline
+    /// return [`Error::Poisoned`] if a call panicked before
+    /// the panic of this call poison the queue and is resumed on the caller
+    /// return [`Error::Cancelled`] if the runtime drop the call without run, like at shutdown
+    /// return [`Error::Closed`] if the queue is closed
+    /// need a [`Runtime`](crate::Runtime), return [`Error::NoRuntime`](crate::Error::NoRuntime) if the queue has no runtime
+    #[inline]
+    pub async fn run_detached<A, T, R>(&self, call: impl FnOnce(A) -> T, arg: A) -> Result<R>
+    where
+        T: Future<Output = R> + Send + 'static,
+        R: Send + 'static,
+    {
+        if holder::is_held(self.id) {
+            return if self.reentrant { Ok(call(arg).await) } else { Err(Error::Reentrant) };
+        }
+        let runtime = runtime::get(self.runtime())?;
+        let guard = self.acquire_arc().await?;
+        let future = holder::hold(self.id, call, arg);
+        detach::spawn(runtime, guard, Some(self.state.clone()), future).await
+    }
+
+    /// Async lock run blocking fn, for the heavy sync work not stall the async worker
+    /// the call run on the blocking thread pool of the runtime once the lock is acquired, the lock is released when the call return
+    /// the call run to completion even if the caller is dropped, the result is discarded
+    /// return [`Error::Reentrant`] instead of deadlock if the current task is already holding this queue and it is not reentrant,
+    /// the nested call of reentrant queue run inline
+    /// return [`Error::Poisoned`] if a call panicked before
+    /// the panic of this call poison the queue and is resumed on the caller
+    /// return [`Error::Cancelled`] if the runtime drop the call without run, like at shutdown
+    /// return [`Error::Closed`] if the queue is closed
+    /// need a [`Runtime`](crate::Runtime), return [`Error::NoRuntime`](crate::Error::NoRuntime) if the queue has no runtime
+    #[inline]
+    pub async fn run_blocking<A, R>(&self, call: impl FnOnce(A) -> R + Send + 'static, arg: A) -> Result<R>
+    where
+        A: Send + 'static,
+        R: Send + 'static,
+    {
+        if holder::is_held(self.id) {
+            return if self.reentrant { Ok(call(arg)) } else { Err(Error::Reentrant) };
+        }
+        let runtime = runtime::get(self.runtime())?;
+        let guard = self.acquire_arc().await?;
+        let id = self.id;
+        detach::spawn_blocking(runtime, guard, Some(self.state.clone()), move || holder::scope(id, || call(arg))).await
+    }
+
+    /// Async lock, return a guard hold the queue until drop
+    /// for the critical section not fit in one closure, the panic while holding the guard poison the queue
+    /// the reentrant check not apply to the guard, see [`AQueueGuard`]
+    /// # Panics
+    /// if the current task is already holding this queue in a call, or the queue is closed or poisoned
+    #[inline]
+    pub async fn lock(&self) -> AQueueGuard<'_> {
+        self.checked_lock().await.unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Async lock, return a guard hold the queue until drop
+    /// for the critical section not fit in one closure, the panic while holding the guard poison the queue
+    /// the reentrant check not apply to the guard, see [`AQueueGuard`]
+    /// return [`Error::Reentrant`] instead of deadlock if the current task is already holding this queue in a call, even if it is reentrant
+    /// return [`Error::Poisoned`] if a call panicked before
+    /// return [`Error::Closed`] if the queue is closed
+    #[inline]
+    pub async fn checked_lock(&self) -> Result<AQueueGuard<'_>> {
+        if holder::is_held(self.id) {
+            return Err(Error::Reentrant);
+        }
+        let guard = self.acquire().await?;
+        Ok(AQueueGuard {
+            _poison: self.state.poison_guard(),
+            _guard: guard,
+        })
+    }
+
+    /// Async lock, return an owned guard hold the queue until drop, it can be moved into a spawned task
+    /// the reentrant check not apply to the guard, see [`AQueueGuard`]
+    /// # Panics
+    /// if the current task is already holding this queue in a call, or the queue is closed or poisoned
+    #[inline]
+    pub async fn lock_arc(&self) -> AQueueGuardArc {
+        self.checked_lock_arc().await.unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Async lock, return an owned guard hold the queue until drop, it can be moved into a spawned task
+    /// the reentrant check not apply to the guard, see [`AQueueGuard`]
+    /// return [`Error::Reentrant`] instead of deadlock if the current task is already holding this queue in a call, even if it is reentrant
+    /// return [`Error::Poisoned`] if a call panicked before
+    /// return [`Error::Closed`] if the queue is closed
+    #[inline]
+    pub async fn checked_lock_arc(&self) -> Result<AQueueGuardArc> {
+        if holder::is_held(self.id) {
+            return Err(Error::Reentrant);
+        }
+        let guard = self.acquire_arc().await?;
+        Ok(AQueueGuardArc {
+            _poison: self.state.poison_guard_arc(),
+            _guard: guard,
+        })
+    }
+}
+
+/// guard of [`AQueue::lock`], the queue is held until drop
+/// the panic while holding the guard poison the queue
+/// # Reentrancy
+/// the reentrant check not apply to the guard, it can move across tasks and threads so the queue can not tell who hold it,
+/// call the queue again on the same task while holding the guard deadlock instead of return [`Error::Reentrant`], drop the guard first
+#[must_use = "the queue is released at once if the guard is not held"]
+pub struct AQueueGuard<'a> {
+    // poison before release the lock
+    _poison: PoisonGuard<&'a State>,
+    _guard: MutexGuard<'a, ()>,
+}
+
+/// owned guard of [`AQueue::lock_arc`], the queue is held until drop
+/// the panic while holding the guard poison the queue
+/// the reentrant check not apply, see [`AQueueGuard`]
+#[must_use = "the queue is released at once if the guard is not held"]
+pub struct AQueueGuardArc {
+    // poison before release the lock
+    _poison: PoisonGuard<Arc<State>>,
+    _guard: MutexGuardArc<()>,
 }
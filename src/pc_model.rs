@@ -1,11 +1,18 @@
 use super::semaphore::SemaphoreQueue;
+use crate::cancel::CancelPolicy;
+use crate::error::Result;
+use crate::runtime::{self, Runtime};
+use crate::state::FullPolicy;
+use crate::time::IntoDeadline;
 use std::future::Future;
+use std::sync::Arc;
 
 /// parallelism control model
 /// The PCModel is a model that can be used to control task parallelism number
-pub struct PCModel<I> {
-    inner: I,
+/// the inner can be unsized, coerce `Arc<PCModel<T>>` or `Box<PCModel<T>>` to `Arc<PCModel<dyn Trait>>`
+pub struct PCModel<I: ?Sized> {
     queue: SemaphoreQueue,
+    inner: I,
 }
 
 impl<I> PCModel<I> {
@@ -18,13 +25,62 @@ impl<I> PCModel<I> {
         }
     }
 
+    /// Create a new PCModel use the runtime for timeout
+    #[inline]
+    pub fn with_runtime(inner: I, n: usize, runtime: impl Runtime) -> Self {
+        PCModel {
+            inner,
+            queue: SemaphoreQueue::with_runtime(n, runtime),
+        }
+    }
+
+    /// Create a bounded PCModel, at most max_pending callers wait for the permit, the running calls are not counted
+    /// the later calls return [`Error::Full`](crate::Error::Full) or wait for space by the policy, with max_pending 0 the call only run if a permit is free now
+    /// # Panics
+    /// if max_pending is 0 and the policy is [`FullPolicy::Wait`](crate::FullPolicy::Wait)
+    #[inline]
+    pub fn bounded(inner: I, n: usize, max_pending: usize, policy: FullPolicy) -> Self {
+        PCModel {
+            inner,
+            queue: SemaphoreQueue::bounded(n, max_pending, policy),
+        }
+    }
+}
+
+impl<I: ?Sized> PCModel<I> {
     /// Get the inner value reference
     #[inline]
     pub fn inner(&self) -> &I {
         &self.inner
     }
 
+    /// a call panicked while running
+    #[inline]
+    pub fn is_poisoned(&self) -> bool {
+        self.queue.is_poisoned()
+    }
+
+    /// clear the poisoned state, make sure the state is consistent before call it
+    #[inline]
+    pub fn clear_poison(&self) {
+        self.queue.clear_poison()
+    }
+
+    /// close the model, the waiting and later calls return [`Error::Closed`](crate::Error::Closed), the running call is not affected
+    #[inline]
+    pub fn close(&self) {
+        self.queue.close()
+    }
+
+    /// is the model closed
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        self.queue.is_closed()
+    }
+
     /// Behavior through queues,thread parallelism control call async fn read ref
+    /// # Panics
+    /// if the model is closed or poisoned
     #[inline]
     pub async fn call<'a, T, R>(&'a self, call: impl FnOnce(&'a I) -> T) -> R
     where
@@ -32,4 +88,128 @@ impl<I> PCModel<I> {
     {
         self.queue.run(call, &self.inner).await
     }
+
+    /// Behavior through queues,thread parallelism control call async fn read ref
+    /// return [`Error::Poisoned`](crate::Error::Poisoned) if a call panicked before
+    /// return [`Error::Closed`](crate::Error::Closed) if the model is closed
+    #[inline]
+    pub async fn checked_call<'a, T, R>(&'a self, call: impl FnOnce(&'a I) -> T) -> Result<R>
+    where
+        T: Future<Output = R>,
+    {
+        self.queue.checked_run(call, &self.inner).await
+    }
+
+    /// Thread parallelism control call fn read ref
+    /// block the current thread until the call finished, for plain threads like FFI callbacks, rayon workers or Drop
+    /// # Panics
+    /// if it need wait on an async worker thread, or the model is closed or poisoned
+    #[inline]
+    pub fn sync_call<R>(&self, call: impl FnOnce(&I) -> R) -> R {
+        self.queue.sync_run(call, &self.inner)
+    }
+
+    /// Thread parallelism control call fn read ref
+    /// block the current thread until the call finished, for plain threads like FFI callbacks, rayon workers or Drop
+    /// return [`Error::AsyncWorker`](crate::Error::AsyncWorker) if it need wait on an async worker thread, park it will stall the executor
+    /// return [`Error::Poisoned`](crate::Error::Poisoned) if a call panicked before
+    /// return [`Error::Closed`](crate::Error::Closed) if the model is closed
+    #[inline]
+    pub fn checked_sync_call<R>(&self, call: impl FnOnce(&I) -> R) -> Result<R> {
+        self.queue.checked_sync_run(call, &self.inner)
+    }
+
+    /// Behavior through queues,thread parallelism control call async fn read ref
+    /// block the current thread until the call finished, for plain threads like FFI callbacks, rayon workers or Drop
+    /// # Panics
+    /// if it is called on an async worker thread, or the model is closed or poisoned
+    #[inline]
+    pub fn block_call<'a, T, R>(&'a self, call: impl FnOnce(&'a I) -> T) -> R
+    where
+        T: Future<Output = R>,
+    {
+        self.queue.block_run(call, &self.inner)
+    }
+
+    /// Behavior through queues,thread parallelism control call async fn read ref
+    /// block the current thread until the call finished, for plain threads like FFI callbacks, rayon workers or Drop
+    /// return [`Error::AsyncWorker`](crate::Error::AsyncWorker) if it is called on an async worker thread, block it will stall the executor
+    /// return [`Error::Poisoned`](crate::Error::Poisoned) if a call panicked before
+    /// return [`Error::Closed`](crate::Error::Closed) if the model is closed
+    #[inline]
+    pub fn checked_block_call<'a, T, R>(&'a self, call: impl FnOnce(&'a I) -> T) -> Result<R>
+    where
+        T: Future<Output = R>,
+    {
+        self.queue.checked_block_run(call, &self.inner)
+    }
+
+    /// Behavior through queues,thread parallelism control call async fn read ref, only if a permit is free now
+    /// return [`Error::WouldBlock`](crate::Error::WouldBlock) at once if the model is busy now, for opportunistic work
+    /// return [`Error::Poisoned`](crate::Error::Poisoned) if a call panicked before
+    /// return [`Error::Closed`](crate::Error::Closed) if the model is closed
+    #[inline]
+    pub async fn try_call<'a, T, R>(&'a self, call: impl FnOnce(&'a I) -> T) -> Result<R>
+    where
+        T: Future<Output = R>,
+    {
+        self.queue.try_run(call, &self.inner).await
+    }
+
+    /// Behavior through queues,thread parallelism control call async fn read ref
+    /// return [`Error::Timeout`](crate::Error::Timeout) if the call is not finished before the timeout, the waiting time is included
+    /// timeout can be a [`Duration`](std::time::Duration) or a deadline [`Instant`](std::time::Instant)
+    /// need a [`Runtime`](crate::Runtime), return [`Error::NoRuntime`](crate::Error::NoRuntime) if the queue has no runtime
+    /// Note: the call is dropped at the timeout even if it is running, the inner may be half updated,
+    /// use [`call_acquire_timeout`](Self::call_acquire_timeout) to only limit the waiting time
+    #[inline]
+    pub async fn call_timeout<'a, T, R>(&'a self, timeout: impl IntoDeadline, call: impl FnOnce(&'a I) -> T) -> Result<R>
+    where
+        T: Future<Output = R>,
+    {
+        runtime::get(self.queue.runtime())?.timeout(timeout, self.checked_call(call)).await?
+    }
+
+    /// Behavior through queues,thread parallelism control call async fn read ref
+    /// return [`Error::Timeout`](crate::Error::Timeout) if the queue is not acquired before the timeout
+    /// once acquired the call always run to completion, the running time is not limited
+    /// timeout can be a [`Duration`](std::time::Duration) or a deadline [`Instant`](std::time::Instant)
+    /// need a [`Runtime`](crate::Runtime), return [`Error::NoRuntime`](crate::Error::NoRuntime) if the queue has no runtime
+    #[inline]
+    pub async fn call_acquire_timeout<'a, T, R>(&'a self, timeout: impl IntoDeadline, call: impl FnOnce(&'a I) -> T) -> Result<R>
+    where
+        T: Future<Output = R>,
+    {
+        self.queue.run_acquire_timeout(timeout, call, &self.inner).await
+    }
+
+    /// Behavior through queues,thread parallelism control call async fn read ref
+    /// return [`Error::Cancelled`](crate::Error::Cancelled) if the cancel signal is ready before the queue is acquired,
+    /// or before the call finished if the policy is [`CancelPolicy::AlsoRunning`]
+    #[inline]
+    pub async fn call_with_cancel<'a, T, R>(&'a self, cancel: impl Future, policy: CancelPolicy, call: impl FnOnce(&'a I) -> T) -> Result<R>
+    where
+        T: Future<Output = R>,
+    {
+        self.queue.run_with_cancel(cancel, policy, call, &self.inner).await
+    }
+
+    /// Thread parallelism control call fn read ref on the blocking thread pool
+    /// for the heavy sync work like compression or big sorts, not stall the async worker
+    /// the call run on the blocking thread pool of the runtime once the permit is acquired, the permit is released when the call return
+    /// the call run to completion even if the caller is dropped, the result is discarded
+    /// return [`Error::Poisoned`](crate::Error::Poisoned) if a call panicked before
+    /// the panic of this call poison the model and is resumed on the caller
+    /// return [`Error::Cancelled`](crate::Error::Cancelled) if the runtime drop the call without run, like at shutdown
+    /// return [`Error::Closed`](crate::Error::Closed) if the model is closed
+    /// need a [`Runtime`](crate::Runtime), return [`Error::NoRuntime`](crate::Error::NoRuntime) if the model has no runtime
+    #[inline]
+    pub async fn call_blocking<R>(self: &Arc<Self>, call: impl FnOnce(&I) -> R + Send + 'static) -> Result<R>
+    where
+        I: Send + Sync + 'static,
+        R: Send + 'static,
+    {
+        // the model is kept alive by the blocking call
+        self.queue.run_blocking(move |model: Arc<Self>| call(&model.inner), self.clone()).await
+    }
 }
@@ -0,0 +1,173 @@
+use crate::error::{Error, Result};
+use crate::time::{IntoDeadline, Timeout};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// boxed send future of runtime
+pub type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send + 'static>>;
+
+/// async runtime used by queues for sleep, timeout, spawn and the blocking calls
+/// enable feature "tokio_runtime", "async_std_runtime" or "smol_runtime" for the builtin runtimes,
+/// or implement it for a custom timer
+pub trait Runtime: Send + Sync + 'static {
+    /// sleep until the deadline
+    fn sleep_until(&self, deadline: Instant) -> BoxFuture<()>;
+
+    /// spawn a detached task
+    fn spawn(&self, future: BoxFuture<()>);
+
+    /// run a blocking fn on the blocking thread pool, by default on a new thread
+    #[inline]
+    fn spawn_blocking(&self, f: Box<dyn FnOnce() + Send>) {
+        thread::spawn(f);
+    }
+
+    /// sleep for the duration
+    #[inline]
+    fn sleep(&self, duration: Duration) -> BoxFuture<()> {
+        self.sleep_until(duration.into_deadline())
+    }
+}
+
+impl dyn Runtime {
+    /// run the future until the timeout
+    /// return [`Error::Timeout`](crate::Error::Timeout) if the future is not ready before the timeout
+    #[inline]
+    pub fn timeout<F: Future>(&self, timeout: impl IntoDeadline, future: F) -> Timeout<F> {
+        Timeout::new(future, self.sleep_until(timeout.into_deadline()))
+    }
+}
+
+impl<R: Runtime + ?Sized> Runtime for Arc<R> {
+    #[inline]
+    fn sleep_until(&self, deadline: Instant) -> BoxFuture<()> {
+        (**self).sleep_until(deadline)
+    }
+
+    #[inline]
+    fn spawn(&self, future: BoxFuture<()>) {
+        (**self).spawn(future)
+    }
+
+    #[inline]
+    fn spawn_blocking(&self, f: Box<dyn FnOnce() + Send>) {
+        (**self).spawn_blocking(f)
+    }
+}
+
+/// tokio runtime
+/// need on feature "tokio_runtime"
+#[cfg(feature = "tokio_runtime")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TokioRuntime;
+
+#[cfg(feature = "tokio_runtime")]
+impl Runtime for TokioRuntime {
+    #[inline]
+    fn sleep_until(&self, deadline: Instant) -> BoxFuture<()> {
+        Box::pin(tokio::time::sleep_until(tokio::time::Instant::from_std(deadline)))
+    }
+
+    #[inline]
+    fn spawn(&self, future: BoxFuture<()>) {
+        tokio::spawn(future);
+    }
+
+    #[inline]
+    fn spawn_blocking(&self, f: Box<dyn FnOnce() + Send>) {
+        tokio::task::spawn_blocking(f);
+    }
+}
+
+/// async-std runtime
+/// need on feature "async_std_runtime"
+#[cfg(feature = "async_std_runtime")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AsyncStdRuntime;
+
+#[cfg(feature = "async_std_runtime")]
+impl Runtime for AsyncStdRuntime {
+    #[inline]
+    fn sleep_until(&self, deadline: Instant) -> BoxFuture<()> {
+        Box::pin(async_std::task::sleep(deadline.saturating_duration_since(Instant::now())))
+    }
+
+    #[inline]
+    fn spawn(&self, future: BoxFuture<()>) {
+        async_std::task::spawn(future);
+    }
+
+    #[inline]
+    fn spawn_blocking(&self, f: Box<dyn FnOnce() + Send>) {
+        async_std::task::spawn_blocking(f);
+    }
+}
+
+/// smol runtime
+/// need on feature "smol_runtime"
+#[cfg(feature = "smol_runtime")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SmolRuntime;
+
+#[cfg(feature = "smol_runtime")]
+impl Runtime for SmolRuntime {
+    #[inline]
+    fn sleep_until(&self, deadline: Instant) -> BoxFuture<()> {
+        let timer = smol::Timer::at(deadline);
+        Box::pin(async move {
+            timer.await;
+        })
+    }
+
+    #[inline]
+    fn spawn(&self, future: BoxFuture<()>) {
+        smol::spawn(future).detach();
+    }
+
+    #[inline]
+    fn spawn_blocking(&self, f: Box<dyn FnOnce() + Send>) {
+        smol::unblock(f).detach();
+    }
+}
+
+/// the default runtime of the enabled features
+/// tokio if the caller is in a tokio runtime, else async-std, then smol, their timers and executors run without a current runtime
+/// return None if only tokio is enabled and the caller is not in a tokio runtime
+#[inline]
+pub fn default_runtime() -> Option<&'static dyn Runtime> {
+    #[cfg(feature = "tokio_runtime")]
+    if tokio::runtime::Handle::try_current().is_ok() {
+        return Some(&TokioRuntime);
+    }
+    #[cfg(feature = "async_std_runtime")]
+    {
+        Some(&AsyncStdRuntime)
+    }
+    #[cfg(all(feature = "smol_runtime", not(feature = "async_std_runtime")))]
+    {
+        Some(&SmolRuntime)
+    }
+    #[cfg(not(any(feature = "async_std_runtime", feature = "smol_runtime")))]
+    {
+        None
+    }
+}
+
+/// the runtime of queue, or the default runtime
+#[inline]
+pub(crate) fn or_default(runtime: &Option<Arc<dyn Runtime>>) -> Option<&dyn Runtime> {
+    match runtime {
+        Some(runtime) => Some(&**runtime),
+        None => default_runtime(),
+    }
+}
+
+/// get the runtime for timeout, detached and blocking calls
+/// return [`Error::NoRuntime`] if no runtime
+#[inline]
+pub(crate) fn get(runtime: Option<&dyn Runtime>) -> Result<&dyn Runtime> {
+    runtime.ok_or(Error::NoRuntime)
+}
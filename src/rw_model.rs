@@ -1,39 +1,84 @@
 use crate::actor::RefInner;
+use crate::cancel::CancelPolicy;
+use crate::error::Result;
 use crate::inner_store::InnerStore;
+use crate::runtime::{self, Runtime};
+use crate::rwlock::{RwPolicy, RwQueueReadGuard, RwQueueWriteGuard, UpgradableInner};
+use crate::state::FullPolicy;
+use crate::time::IntoDeadline;
+use crate::watch::{Subscribe, Watch};
 use crate::RwQueue;
 use std::future::Future;
+use std::marker::PhantomData;
 use std::ops::{Deref, DerefMut};
+use std::panic::{RefUnwindSafe, UnwindSafe};
+use std::pin::Pin;
+use std::ptr::NonNull;
+use std::sync::Arc;
 
 /// RwMode mut ref
+/// keep a pointer instead of `&mut`, so the nested call of reentrant actor not alias it
 pub struct RefMutInner<'a, T: ?Sized> {
-    pub(crate) value: &'a mut T,
+    value: NonNull<T>,
+    _marker: PhantomData<&'a mut T>,
 }
 
-impl<'a, T> RefMutInner<'a, T> {
+unsafe impl<T: ?Sized + Send> Send for RefMutInner<'_, T> {}
+unsafe impl<T: ?Sized + Sync> Sync for RefMutInner<'_, T> {}
+
+impl<'a, T: ?Sized> RefMutInner<'a, T> {
     #[inline]
     pub fn new(value: &'a mut T) -> Self {
-        Self { value }
+        Self {
+            value: NonNull::from(value),
+            _marker: PhantomData,
+        }
+    }
+
+    /// # Safety
+    /// the value must be valid for `'a`, and only accessed by this or the RefMutInner of the nested calls of reentrant actor,
+    /// which are all derived from the same raw pointer, or only read until the write lock is held like the upgradable call
+    #[inline]
+    pub(crate) unsafe fn from_ptr(value: NonNull<T>) -> Self {
+        Self { value, _marker: PhantomData }
+    }
+
+    #[inline]
+    pub(crate) fn into_ptr(self) -> NonNull<T> {
+        self.value
     }
 }
 
 impl<T: ?Sized> Deref for RefMutInner<'_, T> {
     type Target = T;
     fn deref(&self) -> &Self::Target {
-        self.value
+        unsafe { self.value.as_ref() }
     }
 }
 
 impl<T: ?Sized> DerefMut for RefMutInner<'_, T> {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        self.value
+        unsafe { self.value.as_mut() }
     }
 }
 
 /// RwModel
 /// Ensure Thread safety and high performance reading and writing
-pub struct RwModel<I> {
-    inner: InnerStore<I>,
+/// the inner can be unsized, coerce `Arc<RwModel<T>>` to `Arc<RwModel<dyn Storage>>` to swap the backends behind one type
+///
+/// the read calls share the inner, the model is Sync only if the inner is Send and Sync
+/// ```compile_fail
+/// fn share<T: Sync>(_: T) {}
+/// share(aqueue::RwModel::new(std::cell::Cell::new(0)));
+/// ```
+/// the model is Send only if the inner is Send, for a !Send inner use `Rc<RwModel<I>>` on a local executor like [`Actor`](crate::Actor)
+/// ```compile_fail
+/// fn send<T: Send>(_: T) {}
+/// send(aqueue::RwModel::new(std::rc::Rc::new(0)));
+/// ```
+pub struct RwModel<I: ?Sized> {
     queue: RwQueue,
+    inner: InnerStore<I>,
 }
 
 impl<I: Default> Default for RwModel<I> {
@@ -45,6 +90,14 @@ impl<I: Default> Default for RwModel<I> {
     }
 }
 
+/// the panic of write call poison the model
+/// the read calls run at the same time like a rwlock, share the model between threads need I is Send and Sync
+/// the explicit impl replace the auto impl of InnerStore, which only need I is Send
+unsafe impl<I: ?Sized + Send + Sync> Sync for RwModel<I> {}
+
+impl<I: ?Sized> UnwindSafe for RwModel<I> {}
+impl<I: ?Sized> RefUnwindSafe for RwModel<I> {}
+
 impl<I: 'static> RwModel<I> {
     #[inline]
     pub fn new(x: I) -> RwModel<I> {
@@ -54,33 +107,532 @@ impl<I: 'static> RwModel<I> {
         }
     }
 
-    /// Behavior through queues,thread safe call async fn write ref mut
+    /// Create a model use the runtime for timeout
+    #[inline]
+    pub fn with_runtime(x: I, runtime: impl Runtime) -> RwModel<I> {
+        RwModel {
+            inner: InnerStore::new(x),
+            queue: RwQueue::with_runtime(runtime),
+        }
+    }
+
+    /// Create a bounded model, at most max_pending callers wait for the model, the running calls are not counted
+    /// the later calls return [`Error::Full`](crate::Error::Full) or wait for space by the policy, with max_pending 0 the call only run if the model is free now
+    /// # Panics
+    /// if max_pending is 0 and the policy is [`FullPolicy::Wait`](crate::FullPolicy::Wait)
+    #[inline]
+    pub fn bounded(x: I, max_pending: usize, policy: FullPolicy) -> RwModel<I> {
+        RwModel {
+            inner: InnerStore::new(x),
+            queue: RwQueue::bounded(max_pending, policy),
+        }
+    }
+
+    /// Create a model with the policy of who go first when the read and write calls wait at the same time
+    /// like [`RwPolicy::WriterPreferring`] for write heavy config, [`RwPolicy::ReaderPreferring`] for read mostly lookup
+    #[inline]
+    pub fn with_policy(x: I, policy: RwPolicy) -> RwModel<I> {
+        RwModel {
+            inner: InnerStore::new(x),
+            queue: RwQueue::with_policy(policy),
+        }
+    }
+}
+
+impl<I: ?Sized + 'static> RwModel<I> {
+    /// the ref mut of inner for the write calls, it keep a pointer and is deref only after the write lock is held,
+    /// create `&mut I` before that would alias the `&I` of the running readers
+    #[inline]
+    fn inner_mut(&self) -> RefMutInner<'_, I> {
+        unsafe { RefMutInner::from_ptr(self.inner.as_ptr()) }
+    }
+
+    /// the policy of who go first, default is [`RwPolicy::WriterPreferring`]
+    #[inline]
+    pub fn policy(&self) -> RwPolicy {
+        self.queue.policy()
+    }
+
+    /// a write call panicked while holding the model
+    #[inline]
+    pub fn is_poisoned(&self) -> bool {
+        self.queue.is_poisoned()
+    }
+
+    /// clear the poisoned state, make sure the inner is consistent before call it
+    #[inline]
+    pub fn clear_poison(&self) {
+        self.queue.clear_poison()
+    }
+
+    /// close the model, the waiting and later calls return [`Error::Closed`](crate::Error::Closed), the running call is not affected
+    #[inline]
+    pub fn close(&self) {
+        self.queue.close()
+    }
+
+    /// is the model closed
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        self.queue.is_closed()
+    }
+
+    /// the version of model, it is changed after every finished write call
+    #[inline]
+    pub fn version(&self) -> u64 {
+        self.queue.version()
+    }
+
+    /// subscribe the changes of model, the stream wake after every finished write call and yield the version
+    /// the slow consumer only get the latest version, the stream end if the model is closed
+    #[inline]
+    pub fn subscribe(&self) -> Subscribe {
+        self.queue.subscribe()
+    }
+
+    /// watch the state of model, the stream wake after every finished write call and yield the version and the projection of the new state
+    /// the projection is run in a read call, the slow consumer only get the latest state, the stream end if the model is closed or poisoned
+    /// ```
+    /// use aqueue::RwModel;
+    /// use futures_util::StreamExt;
+    /// use std::sync::Arc;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let config = Arc::new(RwModel::new(vec![1]));
+    ///     let mut watch = config.watch(|inner| inner.len());
+    ///     config.call_mut(async move |mut inner| inner.push(2)).await;
+    ///     assert_eq!(watch.next().await, Some((1, 2)));
+    /// }
+    /// ```
     #[inline]
-    pub async fn call_mut<'a, T, R>(&'a self, call: impl FnOnce(RefMutInner<'a, I>) -> T) -> R
+    pub fn watch<T: Send + 'static>(self: &Arc<Self>, project: impl Fn(&I) -> T + Send + Sync + 'static) -> Watch<T>
     where
-        T: Future<Output = R>,
+        I: Send + Sync,
     {
-        self.queue.write_run(call, self.inner.get_mut()).await
+        let model = self.clone();
+        let project = Arc::new(project);
+        Watch::new(self.queue.subscribe(), move || {
+            let (model, project) = (model.clone(), project.clone());
+            Box::pin(async move { model.checked_call(async |inner| (model.version(), project(&inner))).await })
+        })
+    }
+
+    /// Behavior through queues,thread safe call async fn write ref mut
+    /// the ref mut is only usable while the write lock is held, the async closure can not return or store it out of the call
+    /// ```compile_fail
+    /// # async fn leak(model: &aqueue::RwModel<u32>) {
+    /// let inner = model.call_mut(async |inner| inner).await;
+    /// # }
+    /// ```
+    /// ```compile_fail
+    /// # async fn leak(model: &aqueue::RwModel<u32>) {
+    /// let mut slot = None;
+    /// model.call_mut(async |inner| slot = Some(inner)).await;
+    /// # }
+    /// ```
+    /// # Panics
+    /// if call it again inside the call of this model, or the model is closed or poisoned
+    #[inline]
+    pub async fn call_mut<R>(&self, call: impl AsyncFnOnce(RefMutInner<'_, I>) -> R) -> R {
+        self.checked_call_mut(call).await.unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Behavior through queues,thread safe call async fn write ref mut
+    /// return [`Error::Reentrant`](crate::Error::Reentrant) instead of deadlock if call it again inside the call of this model
+    /// return [`Error::Poisoned`](crate::Error::Poisoned) if a write call panicked before
+    /// return [`Error::Closed`](crate::Error::Closed) if the model is closed
+    #[inline]
+    pub async fn checked_call_mut<R>(&self, call: impl AsyncFnOnce(RefMutInner<'_, I>) -> R) -> Result<R> {
+        self.queue.checked_write_run_inner(|inner| call(inner), self.inner_mut()).await
+    }
+
+    /// Behavior through queues,thread safe call async fn write ref mut
+    /// block the current thread until the call finished, for plain threads like FFI callbacks, rayon workers or Drop
+    /// # Panics
+    /// if it is called on an async worker thread, or call it again inside the call of this model, or the model is closed or poisoned
+    #[inline]
+    pub fn block_call_mut<R>(&self, call: impl AsyncFnOnce(RefMutInner<'_, I>) -> R) -> R {
+        self.checked_block_call_mut(call).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Behavior through queues,thread safe call async fn write ref mut
+    /// block the current thread until the call finished, for plain threads like FFI callbacks, rayon workers or Drop
+    /// return [`Error::AsyncWorker`](crate::Error::AsyncWorker) if it is called on an async worker thread, block it will stall the executor
+    /// return [`Error::Reentrant`](crate::Error::Reentrant) if call it again inside the call of this model
+    /// return [`Error::Poisoned`](crate::Error::Poisoned) if a write call panicked before
+    /// return [`Error::Closed`](crate::Error::Closed) if the model is closed
+    #[inline]
+    pub fn checked_block_call_mut<R>(&self, call: impl AsyncFnOnce(RefMutInner<'_, I>) -> R) -> Result<R> {
+        self.queue.checked_block_write_run_inner(|inner| call(inner), self.inner_mut())
+    }
+
+    /// Behavior through queues,thread safe call async fn write ref mut, only if the model is idle now
+    /// return [`Error::WouldBlock`](crate::Error::WouldBlock) at once if the model is busy now, for opportunistic work
+    /// return [`Error::Reentrant`](crate::Error::Reentrant) if call it again inside the call of this model
+    /// return [`Error::Poisoned`](crate::Error::Poisoned) if a write call panicked before
+    /// return [`Error::Closed`](crate::Error::Closed) if the model is closed
+    #[inline]
+    pub async fn try_call_mut<R>(&self, call: impl AsyncFnOnce(RefMutInner<'_, I>) -> R) -> Result<R> {
+        self.queue.try_write_run_inner(|inner| call(inner), self.inner_mut()).await
+    }
+
+    /// Behavior through queues,thread safe call async fn read ref
+    /// the ref is only usable while the read lock is held, the async closure can not return a ref of the inner out of the call
+    /// ```compile_fail
+    /// # async fn leak(model: &aqueue::RwModel<(u32, u32)>) {
+    /// let field: &u32 = model.call(async |inner| &inner.0).await;
+    /// # }
+    /// ```
+    /// ```compile_fail
+    /// # async fn leak(model: &aqueue::RwModel<u32>) {
+    /// let inner = model.call(async |inner| inner).await;
+    /// # }
+    /// ```
+    /// # Panics
+    /// if call it again inside the call of this model, or the model is closed or poisoned
+    #[inline]
+    pub async fn call<R>(&self, call: impl AsyncFnOnce(RefInner<'_, I>) -> R) -> R {
+        self.queue.read_run(|inner| call(inner), unsafe { self.inner.get() }).await
+    }
+
+    /// Behavior through queues,thread safe call async fn read ref
+    /// return [`Error::Reentrant`](crate::Error::Reentrant) instead of deadlock if call it again inside the call of this model
+    /// return [`Error::Poisoned`](crate::Error::Poisoned) if a write call panicked before
+    /// return [`Error::Closed`](crate::Error::Closed) if the model is closed
+    #[inline]
+    pub async fn checked_call<R>(&self, call: impl AsyncFnOnce(RefInner<'_, I>) -> R) -> Result<R> {
+        self.queue.checked_read_run(|inner| call(inner), unsafe { self.inner.get() }).await
+    }
+
+    /// Behavior through queues,thread safe call async fn upgradable read ref
+    /// the call share the model with the read calls, and can upgrade to write without release the lock, like look up a cache and insert if missing
+    /// at most one upgradable call run at a time, the panic after upgrade poison the model
+    /// ```
+    /// # async fn cache(model: &aqueue::RwModel<std::collections::HashMap<u32, String>>) -> String {
+    /// model
+    ///     .call_upgradable(async move |mut inner| {
+    ///         if let Some(value) = inner.get(&1) {
+    ///             return value.clone();
+    ///         }
+    ///         inner.upgrade().await.entry(1).or_insert_with(|| "one".to_string()).clone()
+    ///     })
+    ///     .await
+    /// # }
+    /// ```
+    /// # Panics
+    /// if call it again inside the call of this model, or the model is closed or poisoned
+    #[inline]
+    pub async fn call_upgradable<R>(&self, call: impl AsyncFnOnce(UpgradableInner<'_, I>) -> R) -> R {
+        self.checked_call_upgradable(call).await.unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Behavior through queues,thread safe call async fn upgradable read ref
+    /// return [`Error::Reentrant`](crate::Error::Reentrant) instead of deadlock if call it again inside the call of this model
+    /// return [`Error::Poisoned`](crate::Error::Poisoned) if a write call panicked before
+    /// return [`Error::Closed`](crate::Error::Closed) if the model is closed
+    #[inline]
+    pub async fn checked_call_upgradable<R>(&self, call: impl AsyncFnOnce(UpgradableInner<'_, I>) -> R) -> Result<R> {
+        self.queue.checked_upgradable_read_run_inner(|inner| call(inner), self.inner_mut()).await
     }
 
     /// Behavior through queues,thread safe call async fn read ref
+    /// block the current thread until the call finished, for plain threads like FFI callbacks, rayon workers or Drop
+    /// # Panics
+    /// if it is called on an async worker thread, or call it again inside the call of this model, or the model is closed or poisoned
+    #[inline]
+    pub fn block_call<R>(&self, call: impl AsyncFnOnce(RefInner<'_, I>) -> R) -> R {
+        self.queue.block_read_run(|inner| call(inner), unsafe { self.inner.get() })
+    }
+
+    /// Behavior through queues,thread safe call async fn read ref
+    /// block the current thread until the call finished, for plain threads like FFI callbacks, rayon workers or Drop
+    /// return [`Error::AsyncWorker`](crate::Error::AsyncWorker) if it is called on an async worker thread, block it will stall the executor
+    /// return [`Error::Reentrant`](crate::Error::Reentrant) if call it again inside the call of this model
+    /// return [`Error::Poisoned`](crate::Error::Poisoned) if a write call panicked before
+    /// return [`Error::Closed`](crate::Error::Closed) if the model is closed
+    #[inline]
+    pub fn checked_block_call<R>(&self, call: impl AsyncFnOnce(RefInner<'_, I>) -> R) -> Result<R> {
+        self.queue.checked_block_read_run(|inner| call(inner), unsafe { self.inner.get() })
+    }
+
+    /// Behavior through queues,thread safe call async fn read ref, only if the model is not writing now
+    /// return [`Error::WouldBlock`](crate::Error::WouldBlock) at once if the model is busy now, for opportunistic work
+    /// return [`Error::Reentrant`](crate::Error::Reentrant) if call it again inside the call of this model
+    /// return [`Error::Poisoned`](crate::Error::Poisoned) if a write call panicked before
+    /// return [`Error::Closed`](crate::Error::Closed) if the model is closed
+    #[inline]
+    pub async fn try_call<R>(&self, call: impl AsyncFnOnce(RefInner<'_, I>) -> R) -> Result<R> {
+        self.queue.try_read_run(|inner| call(inner), unsafe { self.inner.get() }).await
+    }
+
+    /// Behavior through queues,thread safe call async fn write ref mut
+    /// return [`Error::Timeout`](crate::Error::Timeout) if the call is not finished before the timeout, the waiting time is included
+    /// timeout can be a [`Duration`](std::time::Duration) or a deadline [`Instant`](std::time::Instant)
+    /// need a [`Runtime`](crate::Runtime), return [`Error::NoRuntime`](crate::Error::NoRuntime) if the queue has no runtime
+    /// Note: the call is dropped at the timeout even if it is running, the inner may be half updated,
+    /// use [`call_mut_acquire_timeout`](Self::call_mut_acquire_timeout) to only limit the waiting time
+    #[inline]
+    pub async fn call_mut_timeout<R>(&self, timeout: impl IntoDeadline, call: impl AsyncFnOnce(RefMutInner<'_, I>) -> R) -> Result<R> {
+        runtime::get(self.queue.runtime())?.timeout(timeout, self.checked_call_mut(call)).await?
+    }
+
+    /// Behavior through queues,thread safe call async fn write ref mut
+    /// return [`Error::Timeout`](crate::Error::Timeout) if the queue is not acquired before the timeout
+    /// once acquired the call always run to completion, the running time is not limited
+    /// timeout can be a [`Duration`](std::time::Duration) or a deadline [`Instant`](std::time::Instant)
+    /// need a [`Runtime`](crate::Runtime), return [`Error::NoRuntime`](crate::Error::NoRuntime) if the queue has no runtime
+    #[inline]
+    pub async fn call_mut_acquire_timeout<R>(&self, timeout: impl IntoDeadline, call: impl AsyncFnOnce(RefMutInner<'_, I>) -> R) -> Result<R> {
+        self.queue
+            .write_run_acquire_timeout_inner(timeout, |inner| call(inner), self.inner_mut())
+            .await
+    }
+
+    /// Behavior through queues,thread safe call async fn write ref mut
+    /// return [`Error::Cancelled`](crate::Error::Cancelled) if the cancel signal is ready before the queue is acquired,
+    /// or before the call finished if the policy is [`CancelPolicy::AlsoRunning`]
+    #[inline]
+    pub async fn call_mut_with_cancel<R>(
+        &self, cancel: impl Future, policy: CancelPolicy, call: impl AsyncFnOnce(RefMutInner<'_, I>) -> R,
+    ) -> Result<R> {
+        self.queue
+            .write_run_with_cancel_inner(cancel, policy, |inner| call(inner), self.inner_mut())
+            .await
+    }
+
+    /// Behavior through queues,thread safe call async fn write ref mut
+    /// the call run to completion even if the caller is dropped, the inner never be half updated by a dropped caller
+    /// once the model is acquired, the call is spawned on the runtime, the result is discarded if the caller is dropped
+    /// return [`Error::Poisoned`](crate::Error::Poisoned) if a write call panicked before
+    /// the panic of this call poison the model and is resumed on the caller
+    /// return [`Error::Cancelled`](crate::Error::Cancelled) if the runtime drop the call without run, like at shutdown
+    /// need a [`Runtime`](crate::Runtime), return [`Error::NoRuntime`](crate::Error::NoRuntime) if the model has no runtime
+    /// the call return a boxed future like `|inner| Box::pin(async move { .. })`, the ref mut can not escape the call
+    #[inline]
+    pub async fn call_mut_detached<R>(
+        self: &Arc<Self>, call: impl for<'g> FnOnce(RefMutInner<'g, I>) -> Pin<Box<dyn Future<Output = R> + Send + 'g>>,
+    ) -> Result<R>
+    where
+        I: Send + Sync,
+        R: Send + 'static,
+    {
+        self.queue
+            .write_run_detached(
+                move |model: Arc<Self>| {
+                    // the model is kept alive by the spawned call, the write lock is held
+                    let future = call(unsafe { RefMutInner::from_ptr(model.inner.as_ptr()) });
+                    async move {
+                        let _model = model;
+                        future.await
+                    }
+                },
+                self.clone(),
+            )
+            .await
+    }
+
+    /// Behavior through queues,thread safe call fn write ref mut on the blocking thread pool
+    /// for the heavy sync work like compression or big sorts, not stall the async worker
+    /// the call run on the blocking thread pool of the runtime once the write lock is acquired, the write lock is released when the call return
+    /// the call run to completion even if the caller is dropped, the result is discarded
+    /// return [`Error::Reentrant`](crate::Error::Reentrant) instead of deadlock if call it again inside the call of this model
+    /// return [`Error::Poisoned`](crate::Error::Poisoned) if a write call panicked before
+    /// the panic of this call poison the model and is resumed on the caller
+    /// return [`Error::Cancelled`](crate::Error::Cancelled) if the runtime drop the call without run, like at shutdown
+    /// return [`Error::Closed`](crate::Error::Closed) if the model is closed
+    /// need a [`Runtime`](crate::Runtime), return [`Error::NoRuntime`](crate::Error::NoRuntime) if the model has no runtime
     #[inline]
-    pub async fn call<'a, T, R>(&'a self, call: impl FnOnce(RefInner<'a, I>) -> T) -> R
+    pub async fn call_mut_blocking<R>(self: &Arc<Self>, call: impl FnOnce(RefMutInner<'_, I>) -> R + Send + 'static) -> Result<R>
     where
-        T: Future<Output = R>,
+        I: Send + Sync + 'static,
+        R: Send + 'static,
     {
-        self.queue.read_run(call, self.inner.get()).await
+        // the model is kept alive by the blocking call
+        self.queue
+            .write_run_blocking(move |model: Arc<Self>| call(model.inner_mut()), self.clone())
+            .await
+    }
+
+    /// Behavior through queues,thread safe call fn read ref on the blocking thread pool
+    /// for the heavy sync work like compression or big sorts, not stall the async worker
+    /// the call run on the blocking thread pool of the runtime once the read lock is acquired, the read lock is released when the call return
+    /// the call run to completion even if the caller is dropped, the result is discarded
+    /// return [`Error::Reentrant`](crate::Error::Reentrant) instead of deadlock if call it again inside the call of this model
+    /// return [`Error::Poisoned`](crate::Error::Poisoned) if a write call panicked before
+    /// the panic of this call is resumed on the caller but not poison the model
+    /// return [`Error::Cancelled`](crate::Error::Cancelled) if the runtime drop the call without run, like at shutdown
+    /// return [`Error::Closed`](crate::Error::Closed) if the model is closed
+    /// need a [`Runtime`](crate::Runtime), return [`Error::NoRuntime`](crate::Error::NoRuntime) if the model has no runtime
+    #[inline]
+    pub async fn call_blocking<R>(self: &Arc<Self>, call: impl FnOnce(&I) -> R + Send + 'static) -> Result<R>
+    where
+        I: Send + Sync + 'static,
+        R: Send + 'static,
+    {
+        // the model is kept alive by the blocking call
+        self.queue
+            .read_run_blocking(move |model: Arc<Self>| call(unsafe { model.inner.get() }), self.clone())
+            .await
+    }
+
+    /// Behavior through queues,thread safe call async fn read ref
+    /// return [`Error::Timeout`](crate::Error::Timeout) if the call is not finished before the timeout, the waiting time is included
+    /// timeout can be a [`Duration`](std::time::Duration) or a deadline [`Instant`](std::time::Instant)
+    /// need a [`Runtime`](crate::Runtime), return [`Error::NoRuntime`](crate::Error::NoRuntime) if the queue has no runtime
+    /// Note: the call is dropped at the timeout even if it is running, the inner may be half updated,
+    /// use [`call_acquire_timeout`](Self::call_acquire_timeout) to only limit the waiting time
+    #[inline]
+    pub async fn call_timeout<R>(&self, timeout: impl IntoDeadline, call: impl AsyncFnOnce(RefInner<'_, I>) -> R) -> Result<R> {
+        runtime::get(self.queue.runtime())?.timeout(timeout, self.checked_call(call)).await?
+    }
+
+    /// Behavior through queues,thread safe call async fn read ref
+    /// return [`Error::Timeout`](crate::Error::Timeout) if the queue is not acquired before the timeout
+    /// once acquired the call always run to completion, the running time is not limited
+    /// timeout can be a [`Duration`](std::time::Duration) or a deadline [`Instant`](std::time::Instant)
+    /// need a [`Runtime`](crate::Runtime), return [`Error::NoRuntime`](crate::Error::NoRuntime) if the queue has no runtime
+    #[inline]
+    pub async fn call_acquire_timeout<R>(&self, timeout: impl IntoDeadline, call: impl AsyncFnOnce(RefInner<'_, I>) -> R) -> Result<R> {
+        self.queue
+            .read_run_acquire_timeout(timeout, |inner| call(inner), unsafe { self.inner.get() })
+            .await
+    }
+
+    /// Behavior through queues,thread safe call async fn read ref
+    /// return [`Error::Cancelled`](crate::Error::Cancelled) if the cancel signal is ready before the queue is acquired,
+    /// or before the call finished if the policy is [`CancelPolicy::AlsoRunning`]
+    #[inline]
+    pub async fn call_with_cancel<R>(&self, cancel: impl Future, policy: CancelPolicy, call: impl AsyncFnOnce(RefInner<'_, I>) -> R) -> Result<R> {
+        self.queue
+            .read_run_with_cancel(cancel, policy, |inner| call(inner), unsafe { self.inner.get() })
+            .await
     }
 
     ///Thread safe call async fn read, Balanced queues are not supported
+    /// # Panics
+    /// if it need wait on an async worker thread, or call it again inside the call of this model, or the model is closed or poisoned
     #[inline]
     pub fn sync_call<R>(&self, call: impl FnOnce(&I) -> R) -> R {
-        self.queue.sync_read_run(call, self.inner.get())
+        self.queue.sync_read_run(call, unsafe { self.inner.get() })
+    }
+
+    ///Thread safe call async fn read, Balanced queues are not supported
+    /// return [`Error::AsyncWorker`](crate::Error::AsyncWorker) if it need wait on an async worker thread, park it will stall the executor
+    /// return [`Error::Reentrant`](crate::Error::Reentrant) if call it again inside the call of this model
+    /// return [`Error::Poisoned`](crate::Error::Poisoned) if a write call panicked before
+    /// return [`Error::Closed`](crate::Error::Closed) if the model is closed
+    #[inline]
+    pub fn checked_sync_call<R>(&self, call: impl FnOnce(&I) -> R) -> Result<R> {
+        self.queue.checked_sync_read_run(call, unsafe { self.inner.get() })
     }
 
     ///Thread safe call async fn write, Balanced queues are not supported
+    /// # Panics
+    /// if it need wait on an async worker thread, or call it again inside the call of this model, or the model is closed or poisoned
     #[inline]
     pub fn sync_mut_call<R>(&self, call: impl FnOnce(RefMutInner<'_, I>) -> R) -> R {
-        self.queue.sync_write_run(call, RefMutInner { value: self.inner.get_mut() })
+        self.queue.sync_write_run(call, self.inner_mut())
+    }
+
+    ///Thread safe call async fn write, Balanced queues are not supported
+    /// return [`Error::AsyncWorker`](crate::Error::AsyncWorker) if it need wait on an async worker thread, park it will stall the executor
+    /// return [`Error::Reentrant`](crate::Error::Reentrant) if call it again inside the call of this model
+    /// return [`Error::Poisoned`](crate::Error::Poisoned) if a write call panicked before
+    /// return [`Error::Closed`](crate::Error::Closed) if the model is closed
+    #[inline]
+    pub fn checked_sync_mut_call<R>(&self, call: impl FnOnce(RefMutInner<'_, I>) -> R) -> Result<R> {
+        self.queue.checked_sync_write_run(call, self.inner_mut())
+    }
+
+    /// Async read lock, return a guard deref to the inner until drop
+    /// for the critical section not fit in one closure
+    /// the reentrant check not apply to the guard, see [`RwModelWriteGuard`]
+    /// # Panics
+    /// if call it inside the call of this model, or the model is closed or poisoned
+    #[inline]
+    pub async fn read(&self) -> RwModelReadGuard<'_, I> {
+        self.checked_read().await.unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Async read lock, return a guard deref to the inner until drop
+    /// for the critical section not fit in one closure
+    /// the reentrant check not apply to the guard, see [`RwModelWriteGuard`]
+    /// return [`Error::Reentrant`](crate::Error::Reentrant) if call it inside the call of this model
+    /// return [`Error::Poisoned`](crate::Error::Poisoned) if a write call panicked before
+    /// return [`Error::Closed`](crate::Error::Closed) if the model is closed
+    #[inline]
+    pub async fn checked_read(&self) -> Result<RwModelReadGuard<'_, I>> {
+        let guard = self.queue.checked_read().await?;
+        Ok(RwModelReadGuard {
+            inner: unsafe { self.inner.get() },
+            _guard: guard,
+        })
+    }
+
+    /// Async write lock, return a guard deref mut to the inner until drop, the panic while holding the guard poison the model
+    /// for the critical section not fit in one closure
+    /// the reentrant check not apply to the guard, see [`RwModelWriteGuard`]
+    /// # Panics
+    /// if call it inside the call of this model, or the model is closed or poisoned
+    #[inline]
+    pub async fn write(&self) -> RwModelWriteGuard<'_, I> {
+        self.checked_write().await.unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Async write lock, return a guard deref mut to the inner until drop, the panic while holding the guard poison the model
+    /// for the critical section not fit in one closure
+    /// the reentrant check not apply to the guard, see [`RwModelWriteGuard`]
+    /// return [`Error::Reentrant`](crate::Error::Reentrant) if call it inside the call of this model
+    /// return [`Error::Poisoned`](crate::Error::Poisoned) if a write call panicked before
+    /// return [`Error::Closed`](crate::Error::Closed) if the model is closed
+    #[inline]
+    pub async fn checked_write(&self) -> Result<RwModelWriteGuard<'_, I>> {
+        let guard = self.queue.checked_write().await?;
+        Ok(RwModelWriteGuard {
+            inner: self.inner_mut(),
+            _guard: guard,
+        })
+    }
+}
+
+/// guard of [`RwModel::read`], deref to the inner, the read lock is held until drop
+/// the reentrant check not apply, see [`RwModelWriteGuard`]
+#[must_use = "the read lock is released at once if the guard is not held"]
+pub struct RwModelReadGuard<'a, I: ?Sized> {
+    inner: &'a I,
+    _guard: RwQueueReadGuard<'a>,
+}
+
+impl<I: ?Sized> Deref for RwModelReadGuard<'_, I> {
+    type Target = I;
+
+    fn deref(&self) -> &Self::Target {
+        self.inner
+    }
+}
+
+/// guard of [`RwModel::write`], deref mut to the inner, the write lock is held until drop
+/// the panic while holding the guard poison the model
+/// # Reentrancy
+/// the reentrant check not apply to the guard, it can move across tasks and threads so the model can not tell who hold it,
+/// call the model again on the same task while holding the guard deadlock instead of return [`Error::Reentrant`](crate::Error::Reentrant), drop the guard first
+#[must_use = "the write lock is released at once if the guard is not held"]
+pub struct RwModelWriteGuard<'a, I: ?Sized> {
+    inner: RefMutInner<'a, I>,
+    _guard: RwQueueWriteGuard<'a>,
+}
+
+impl<I: ?Sized> Deref for RwModelWriteGuard<'_, I> {
+    type Target = I;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<I: ?Sized> DerefMut for RwModelWriteGuard<'_, I> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
     }
 }
@@ -1,76 +1,841 @@
 use crate::actor::RefInner;
+use crate::blocking;
+use crate::cancel::{CancelPolicy, Cancellable};
+use crate::detach;
+use crate::error::{Error, Result};
+use crate::holder;
+use crate::runtime::{self, Runtime};
 use crate::rw_model::RefMutInner;
-use async_lock::RwLock;
+use crate::state::{FullPolicy, PoisonGuard, State};
+use crate::time::IntoDeadline;
+use crate::watch::Subscribe;
+use policy::{PolicyLock, ReadGuard, ReadGuardArc, UpgradableGuardArc, WriteGuard, WriteGuardArc};
 use std::future::Future;
-use std::hint::spin_loop;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::panic::{RefUnwindSafe, UnwindSafe};
+use std::pin::{pin, Pin};
+use std::ptr::NonNull;
+use std::sync::Arc;
+
+mod policy;
+
+pub use policy::RwPolicy;
 
 /// async future thread safe mutex for Rwlock
 pub struct RwQueue {
-    lock: RwLock<()>,
+    id: usize,
+    state: Arc<State>,
+    runtime: Option<Arc<dyn Runtime>>,
+    lock: PolicyLock,
 }
 
 impl Default for RwQueue {
     #[inline]
     fn default() -> Self {
-        RwQueue { lock: RwLock::new(()) }
+        RwQueue {
+            id: holder::next_id(),
+            state: Arc::default(),
+            runtime: None,
+            lock: PolicyLock::new(RwPolicy::default()),
+        }
     }
 }
 
+/// the panic of write call poison the queue
+impl UnwindSafe for RwQueue {}
+impl RefUnwindSafe for RwQueue {}
+
 impl RwQueue {
     #[inline]
     pub fn new() -> RwQueue {
         RwQueue::default()
     }
 
+    /// Create a queue use the runtime for timeout
+    #[inline]
+    pub fn with_runtime(runtime: impl Runtime) -> RwQueue {
+        RwQueue {
+            runtime: Some(Arc::new(runtime)),
+            ..RwQueue::default()
+        }
+    }
+
+    /// Create a bounded queue, at most max_pending callers wait for the lock, the holders of the lock are not counted
+    /// the later calls return [`Error::Full`] or wait for space by the policy, with max_pending 0 the call only run if the lock is free now
+    /// # Panics
+    /// if max_pending is 0 and the policy is [`FullPolicy::Wait`](crate::FullPolicy::Wait)
+    #[inline]
+    pub fn bounded(max_pending: usize, policy: FullPolicy) -> RwQueue {
+        RwQueue {
+            state: Arc::new(State::bounded(max_pending, policy)),
+            ..RwQueue::default()
+        }
+    }
+
+    /// Create a queue with the policy of who go first when the readers and writers wait at the same time
+    #[inline]
+    pub fn with_policy(policy: RwPolicy) -> RwQueue {
+        RwQueue {
+            lock: PolicyLock::new(policy),
+            ..RwQueue::default()
+        }
+    }
+
+    /// the policy of who go first, default is [`RwPolicy::WriterPreferring`]
+    #[inline]
+    pub fn policy(&self) -> RwPolicy {
+        self.lock.policy()
+    }
+
+    /// the runtime of timeout, set by with_runtime or the default runtime of the enabled features
+    #[inline]
+    pub fn runtime(&self) -> Option<&dyn Runtime> {
+        runtime::or_default(&self.runtime)
+    }
+
+    /// close the queue, the waiting and later calls return [`Error::Closed`], the running call is not affected
+    #[inline]
+    pub fn close(&self) {
+        self.state.close()
+    }
+
+    /// is the queue closed
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        self.state.is_closed()
+    }
+
+    /// a write call panicked while holding the queue
+    /// Note: the panic of read call will not poison the queue
+    #[inline]
+    pub fn is_poisoned(&self) -> bool {
+        self.state.is_poisoned()
+    }
+
+    /// clear the poisoned state, make sure the state is consistent before call it
+    #[inline]
+    pub fn clear_poison(&self) {
+        self.state.clear_poison()
+    }
+
+    /// the count of the finished write calls
+    #[inline]
+    pub(crate) fn version(&self) -> u64 {
+        self.state.version()
+    }
+
+    /// stream of the version, changed after every finished write call
+    #[inline]
+    pub(crate) fn subscribe(&self) -> Subscribe {
+        Subscribe::new(self.state.clone())
+    }
+
+    /// wait the write lock, the caller is pending until the lock is acquired
+    #[inline]
+    async fn acquire_write(&self) -> Result<WriteGuard<'_>> {
+        self.state.enter(|| self.lock.try_write(), || self.lock.write()).await
+    }
+
+    /// wait the read lock, the caller is pending until the lock is acquired
+    #[inline]
+    async fn acquire_read(&self) -> Result<ReadGuard<'_>> {
+        self.state.enter(|| self.lock.try_read(), || self.lock.read()).await
+    }
+
+    /// wait the owned write lock, the caller is pending until the lock is acquired
+    #[inline]
+    async fn acquire_write_arc(&self) -> Result<WriteGuardArc> {
+        self.state.enter(|| self.lock.try_write_arc(), || self.lock.write_arc()).await
+    }
+
+    /// wait the owned read lock, the caller is pending until the lock is acquired
+    #[inline]
+    async fn acquire_read_arc(&self) -> Result<ReadGuardArc> {
+        self.state.enter(|| self.lock.try_read_arc(), || self.lock.read_arc()).await
+    }
+
+    /// wait the owned upgradable read lock, the caller is pending until the lock is acquired
+    #[inline]
+    async fn acquire_upgradable_arc(&self) -> Result<UpgradableGuardArc> {
+        self.state
+            .enter(|| self.lock.try_upgradable_read_arc(), || self.lock.upgradable_read_arc())
+            .await
+    }
+
     /// Sync write run fn
-    /// Note: it is not based on fair lock. It will never be called when the mutex has unprocessed
+    /// spin briefly, then park the thread and wait in first in, first run order with the async callers
+    /// # Panics
+    /// if it need wait on an async worker thread, or the current task is already holding this queue, or the queue is closed or poisoned
     #[inline]
-    pub fn sync_write_run<A, R>(&self, call: impl FnOnce(RefMutInner<'_, A>) -> R, arg: RefMutInner<'_, A>) -> R {
-        loop {
-            let guard = self.lock.try_write();
-            if guard.is_some() {
-                return call(arg);
-            } else {
-                spin_loop();
-            }
+    pub fn sync_write_run<A: ?Sized, R>(&self, call: impl FnOnce(RefMutInner<'_, A>) -> R, arg: RefMutInner<'_, A>) -> R {
+        self.checked_sync_write_run(call, arg).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Sync write run fn
+    /// return [`Error::AsyncWorker`] if it need wait on an async worker thread, park it will stall the executor and the holder may never release the queue
+    /// return [`Error::Reentrant`] if the current task is already holding this queue
+    /// return [`Error::Poisoned`] if a write call panicked before
+    /// return [`Error::Closed`] if the queue is closed
+    #[inline]
+    pub fn checked_sync_write_run<A: ?Sized, R>(&self, call: impl FnOnce(RefMutInner<'_, A>) -> R, arg: RefMutInner<'_, A>) -> Result<R> {
+        if holder::is_held(self.id) {
+            return Err(Error::Reentrant);
         }
+        let _guard = self.state.sync_enter(
+            || self.lock.try_write(),
+            || blocking::acquire(|| self.lock.try_write(), || self.lock.write_blocking()),
+        )?;
+        let _poison = self.state.poison_guard();
+        Ok(holder::scope(self.id, || call(arg)))
     }
 
     /// Sync run fn
-    /// Note: it is not based on fair lock. It will never be called when the mutex has unprocessed
+    /// spin briefly, then park the thread and wait in first in, first run order with the async callers
+    /// # Panics
+    /// if it need wait on an async worker thread, or the current task is already holding this queue, or the queue is closed or poisoned
     #[inline]
     pub fn sync_read_run<A, R>(&self, call: impl FnOnce(A) -> R, arg: A) -> R {
-        loop {
-            let guard = self.lock.try_read();
-            if guard.is_some() {
-                return call(arg);
-            } else {
-                spin_loop();
-            }
+        self.checked_sync_read_run(call, arg).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Sync run fn
+    /// return [`Error::AsyncWorker`] if it need wait on an async worker thread, park it will stall the executor and the holder may never release the queue
+    /// return [`Error::Reentrant`] if the current task is already holding this queue
+    /// return [`Error::Poisoned`] if a write call panicked before
+    /// return [`Error::Closed`] if the queue is closed
+    #[inline]
+    pub fn checked_sync_read_run<A, R>(&self, call: impl FnOnce(A) -> R, arg: A) -> Result<R> {
+        if holder::is_held(self.id) {
+            return Err(Error::Reentrant);
         }
+        let _guard = self.state.sync_enter(
+            || self.lock.try_read(),
+            || blocking::acquire(|| self.lock.try_read(), || self.lock.read_blocking()),
+        )?;
+        Ok(holder::scope(self.id, || call(arg)))
     }
 
     /// Async write run fn
     /// It is based on the principle of first in, first run
+    /// # Panics
+    /// if the current task is already holding this queue, or the queue is closed or poisoned
     #[inline]
-    pub async fn write_run<'a, A, T, R>(&self, call: impl FnOnce(RefMutInner<'a, A>) -> T, arg: &'a mut A) -> R
+    pub async fn write_run<'a, A: ?Sized, T, R>(&self, call: impl FnOnce(RefMutInner<'a, A>) -> T, arg: &'a mut A) -> R
     where
         T: Future<Output = R>,
     {
-        let arg = RefMutInner { value: arg };
-        let _guard = self.lock.write().await;
-        call(arg).await
+        self.checked_write_run(call, arg).await.unwrap_or_else(|err| panic!("{}", err))
     }
 
     /// Async write run fn
+    /// return [`Error::Reentrant`] instead of deadlock if the current task is already holding this queue
+    /// return [`Error::Poisoned`] if a write call panicked before
+    /// return [`Error::Closed`] if the queue is closed
+    #[inline]
+    pub async fn checked_write_run<'a, A: ?Sized, T, R>(&self, call: impl FnOnce(RefMutInner<'a, A>) -> T, arg: &'a mut A) -> Result<R>
+    where
+        T: Future<Output = R>,
+    {
+        self.checked_write_run_inner(call, RefMutInner::new(arg)).await
+    }
+
+    /// the arg can point to the shared inner of RwModel, it is deref only after the write lock is held
+    #[inline]
+    pub(crate) async fn checked_write_run_inner<'a, A: ?Sized, T, R>(
+        &self, call: impl FnOnce(RefMutInner<'a, A>) -> T, arg: RefMutInner<'a, A>,
+    ) -> Result<R>
+    where
+        T: Future<Output = R>,
+    {
+        if holder::is_held(self.id) {
+            return Err(Error::Reentrant);
+        }
+        let _guard = self.acquire_write().await?;
+        let _poison = self.state.poison_guard();
+        Ok(holder::hold(self.id, call, arg).await)
+    }
+
+    /// Blocking write run async fn
+    /// block the current thread until the call finished, the async fn is run on the current thread
+    /// it wait in first in, first run order with the async callers, for plain threads like FFI callbacks, rayon workers or Drop
+    /// # Panics
+    /// if it is called on an async worker thread, or the current task is already holding this queue, or the queue is closed or poisoned
+    #[inline]
+    pub fn block_write_run<'a, A: ?Sized, T, R>(&self, call: impl FnOnce(RefMutInner<'a, A>) -> T, arg: &'a mut A) -> R
+    where
+        T: Future<Output = R>,
+    {
+        self.checked_block_write_run(call, arg).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Blocking write run async fn
+    /// block the current thread until the call finished, the async fn is run on the current thread
+    /// it wait in first in, first run order with the async callers, for plain threads like FFI callbacks, rayon workers or Drop
+    /// return [`Error::AsyncWorker`] if it is called on an async worker thread, block it will stall the executor
+    /// return [`Error::Reentrant`] if the current task is already holding this queue
+    /// return [`Error::Poisoned`] if a write call panicked before
+    /// return [`Error::Closed`] if the queue is closed
+    #[inline]
+    pub fn checked_block_write_run<'a, A: ?Sized, T, R>(&self, call: impl FnOnce(RefMutInner<'a, A>) -> T, arg: &'a mut A) -> Result<R>
+    where
+        T: Future<Output = R>,
+    {
+        self.checked_block_write_run_inner(call, RefMutInner::new(arg))
+    }
+
+    /// block on [`Self::checked_write_run_inner`]
+    #[inline]
+    pub(crate) fn checked_block_write_run_inner<'a, A: ?Sized, T, R>(
+        &self, call: impl FnOnce(RefMutInner<'a, A>) -> T, arg: RefMutInner<'a, A>,
+    ) -> Result<R>
+    where
+        T: Future<Output = R>,
+    {
+        if blocking::in_async_worker() {
+            return Err(Error::AsyncWorker);
+        }
+        blocking::block_on(self.checked_write_run_inner(call, arg))
+    }
+
+    /// Async write run fn, only if the lock is free now
+    /// return [`Error::WouldBlock`] at once if the lock is held by others
+    /// return [`Error::Reentrant`] if the current task is already holding this queue
+    /// return [`Error::Poisoned`] if a write call panicked before
+    /// return [`Error::Closed`] if the queue is closed
+    #[inline]
+    pub async fn try_write_run<'a, A: ?Sized, T, R>(&self, call: impl FnOnce(RefMutInner<'a, A>) -> T, arg: &'a mut A) -> Result<R>
+    where
+        T: Future<Output = R>,
+    {
+        self.try_write_run_inner(call, RefMutInner::new(arg)).await
+    }
+
+    /// like [`Self::checked_write_run_inner`], only if the lock is free now
+    #[inline]
+    pub(crate) async fn try_write_run_inner<'a, A: ?Sized, T, R>(
+        &self, call: impl FnOnce(RefMutInner<'a, A>) -> T, arg: RefMutInner<'a, A>,
+    ) -> Result<R>
+    where
+        T: Future<Output = R>,
+    {
+        if holder::is_held(self.id) {
+            return Err(Error::Reentrant);
+        }
+        self.state.check_closed()?;
+        let _guard = self.lock.try_write().ok_or(Error::WouldBlock)?;
+        self.state.check()?;
+        let _poison = self.state.poison_guard();
+        Ok(holder::hold(self.id, call, arg).await)
+    }
+
+    /// Async read run fn
     /// It is based on the principle of first in, first run
+    /// # Panics
+    /// if the current task is already holding this queue, or the queue is closed or poisoned
     #[inline]
-    pub async fn read_run<'a, A, T, R>(&self, call: impl FnOnce(RefInner<'a, A>) -> T, arg: &'a A) -> R
+    pub async fn read_run<'a, A: ?Sized, T, R>(&self, call: impl FnOnce(RefInner<'a, A>) -> T, arg: &'a A) -> R
     where
         T: Future<Output = R>,
     {
+        self.checked_read_run(call, arg).await.unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Async read run fn
+    /// return [`Error::Reentrant`] instead of deadlock if the current task is already holding this queue
+    /// return [`Error::Poisoned`] if a write call panicked before
+    /// return [`Error::Closed`] if the queue is closed
+    #[inline]
+    pub async fn checked_read_run<'a, A: ?Sized, T, R>(&self, call: impl FnOnce(RefInner<'a, A>) -> T, arg: &'a A) -> Result<R>
+    where
+        T: Future<Output = R>,
+    {
+        if holder::is_held(self.id) {
+            return Err(Error::Reentrant);
+        }
         let arg = RefInner { value: arg };
-        let _guard = self.lock.read().await;
-        call(arg).await
+        let _guard = self.acquire_read().await?;
+        Ok(holder::hold(self.id, call, arg).await)
+    }
+
+    /// Async upgradable read run fn
+    /// the call share the queue with the read calls, and can upgrade to write without release the lock, like look up a cache and insert if missing
+    /// at most one upgradable call run at a time, the panic after upgrade poison the queue
+    /// # Panics
+    /// if the current task is already holding this queue, or the queue is closed or poisoned
+    #[inline]
+    pub async fn upgradable_read_run<'a, A: ?Sized, T, R>(&self, call: impl FnOnce(UpgradableInner<'a, A>) -> T, arg: &'a mut A) -> R
+    where
+        T: Future<Output = R>,
+    {
+        self.checked_upgradable_read_run(call, arg).await.unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Async upgradable read run fn
+    /// the call share the queue with the read calls, and can upgrade to write without release the lock, like look up a cache and insert if missing
+    /// at most one upgradable call run at a time, the panic after upgrade poison the queue
+    /// return [`Error::Reentrant`] instead of deadlock if the current task is already holding this queue
+    /// return [`Error::Poisoned`] if a write call panicked before
+    /// return [`Error::Closed`] if the queue is closed
+    #[inline]
+    pub async fn checked_upgradable_read_run<'a, A: ?Sized, T, R>(&self, call: impl FnOnce(UpgradableInner<'a, A>) -> T, arg: &'a mut A) -> Result<R>
+    where
+        T: Future<Output = R>,
+    {
+        self.checked_upgradable_read_run_inner(call, RefMutInner::new(arg)).await
+    }
+
+    /// upgradable read run on the RefMutInner, the `&mut` is created only after upgrade
+    #[inline]
+    pub(crate) async fn checked_upgradable_read_run_inner<'a, A: ?Sized + 'a, T, R>(
+        &self, call: impl FnOnce(UpgradableInner<'a, A>) -> T, arg: RefMutInner<'a, A>,
+    ) -> Result<R>
+    where
+        T: Future<Output = R>,
+    {
+        if holder::is_held(self.id) {
+            return Err(Error::Reentrant);
+        }
+        let guard = self.acquire_upgradable_arc().await?;
+        let arg = UpgradableInner {
+            value: arg.into_ptr(),
+            lock: UpgradableLock::Read(guard),
+            state: self.state.clone(),
+            _marker: PhantomData,
+        };
+        Ok(holder::hold(self.id, call, arg).await)
+    }
+
+    /// Blocking read run async fn
+    /// block the current thread until the call finished, the async fn is run on the current thread
+    /// it wait in first in, first run order with the async callers, for plain threads like FFI callbacks, rayon workers or Drop
+    /// # Panics
+    /// if it is called on an async worker thread, or the current task is already holding this queue, or the queue is closed or poisoned
+    #[inline]
+    pub fn block_read_run<'a, A: ?Sized, T, R>(&self, call: impl FnOnce(RefInner<'a, A>) -> T, arg: &'a A) -> R
+    where
+        T: Future<Output = R>,
+    {
+        self.checked_block_read_run(call, arg).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Blocking read run async fn
+    /// block the current thread until the call finished, the async fn is run on the current thread
+    /// it wait in first in, first run order with the async callers, for plain threads like FFI callbacks, rayon workers or Drop
+    /// return [`Error::AsyncWorker`] if it is called on an async worker thread, block it will stall the executor
+    /// return [`Error::Reentrant`] if the current task is already holding this queue
+    /// return [`Error::Poisoned`] if a write call panicked before
+    /// return [`Error::Closed`] if the queue is closed
+    #[inline]
+    pub fn checked_block_read_run<'a, A: ?Sized, T, R>(&self, call: impl FnOnce(RefInner<'a, A>) -> T, arg: &'a A) -> Result<R>
+    where
+        T: Future<Output = R>,
+    {
+        if blocking::in_async_worker() {
+            return Err(Error::AsyncWorker);
+        }
+        blocking::block_on(self.checked_read_run(call, arg))
+    }
+
+    /// Async read run fn, only if the lock is not write held or waited now
+    /// return [`Error::WouldBlock`] at once if the lock is held by writer
+    /// return [`Error::Reentrant`] if the current task is already holding this queue
+    /// return [`Error::Poisoned`] if a write call panicked before
+    /// return [`Error::Closed`] if the queue is closed
+    #[inline]
+    pub async fn try_read_run<'a, A: ?Sized, T, R>(&self, call: impl FnOnce(RefInner<'a, A>) -> T, arg: &'a A) -> Result<R>
+    where
+        T: Future<Output = R>,
+    {
+        if holder::is_held(self.id) {
+            return Err(Error::Reentrant);
+        }
+        let arg = RefInner { value: arg };
+        self.state.check_closed()?;
+        let _guard = self.lock.try_read().ok_or(Error::WouldBlock)?;
+        self.state.check()?;
+        Ok(holder::hold(self.id, call, arg).await)
+    }
+
+    /// Async write run fn
+    /// return [`Error::Timeout`](crate::Error::Timeout) if the write lock is not acquired before the timeout
+    /// once acquired the call always run to completion, the running time is not limited
+    /// timeout can be a [`Duration`](std::time::Duration) or a deadline [`Instant`](std::time::Instant)
+    /// need a [`Runtime`](crate::Runtime), return [`Error::NoRuntime`](crate::Error::NoRuntime) if the queue has no runtime
+    #[inline]
+    pub async fn write_run_acquire_timeout<'a, A: ?Sized, T, R>(
+        &self, timeout: impl IntoDeadline, call: impl FnOnce(RefMutInner<'a, A>) -> T, arg: &'a mut A,
+    ) -> Result<R>
+    where
+        T: Future<Output = R>,
+    {
+        self.write_run_acquire_timeout_inner(timeout, call, RefMutInner::new(arg)).await
+    }
+
+    /// like [`Self::checked_write_run_inner`], wait the write lock until the timeout
+    #[inline]
+    pub(crate) async fn write_run_acquire_timeout_inner<'a, A: ?Sized, T, R>(
+        &self, timeout: impl IntoDeadline, call: impl FnOnce(RefMutInner<'a, A>) -> T, arg: RefMutInner<'a, A>,
+    ) -> Result<R>
+    where
+        T: Future<Output = R>,
+    {
+        if holder::is_held(self.id) {
+            return Err(Error::Reentrant);
+        }
+        let _guard = runtime::get(self.runtime())?.timeout(timeout, self.acquire_write()).await??;
+        let _poison = self.state.poison_guard();
+        Ok(holder::hold(self.id, call, arg).await)
+    }
+
+    /// Async read run fn
+    /// return [`Error::Timeout`](crate::Error::Timeout) if the read lock is not acquired before the timeout
+    /// once acquired the call always run to completion, the running time is not limited
+    /// timeout can be a [`Duration`](std::time::Duration) or a deadline [`Instant`](std::time::Instant)
+    /// need a [`Runtime`](crate::Runtime), return [`Error::NoRuntime`](crate::Error::NoRuntime) if the queue has no runtime
+    #[inline]
+    pub async fn read_run_acquire_timeout<'a, A: ?Sized, T, R>(
+        &self, timeout: impl IntoDeadline, call: impl FnOnce(RefInner<'a, A>) -> T, arg: &'a A,
+    ) -> Result<R>
+    where
+        T: Future<Output = R>,
+    {
+        if holder::is_held(self.id) {
+            return Err(Error::Reentrant);
+        }
+        let arg = RefInner { value: arg };
+        let _guard = runtime::get(self.runtime())?.timeout(timeout, self.acquire_read()).await??;
+        Ok(holder::hold(self.id, call, arg).await)
+    }
+
+    /// Async write run fn
+    /// return [`Error::Cancelled`](crate::Error::Cancelled) if the cancel signal is ready before the write lock is acquired,
+    /// or before the call finished if the policy is [`CancelPolicy::AlsoRunning`]
+    /// cancel can be any future, example `token.cancelled()` of tokio-util CancellationToken
+    #[inline]
+    pub async fn write_run_with_cancel<'a, A: ?Sized, T, R>(
+        &self, cancel: impl Future, policy: CancelPolicy, call: impl FnOnce(RefMutInner<'a, A>) -> T, arg: &'a mut A,
+    ) -> Result<R>
+    where
+        T: Future<Output = R>,
+    {
+        self.write_run_with_cancel_inner(cancel, policy, call, RefMutInner::new(arg)).await
+    }
+
+    /// like [`Self::checked_write_run_inner`], until the cancel signal is ready
+    #[inline]
+    pub(crate) async fn write_run_with_cancel_inner<'a, A: ?Sized, T, R>(
+        &self, cancel: impl Future, policy: CancelPolicy, call: impl FnOnce(RefMutInner<'a, A>) -> T, arg: RefMutInner<'a, A>,
+    ) -> Result<R>
+    where
+        T: Future<Output = R>,
+    {
+        if holder::is_held(self.id) {
+            return Err(Error::Reentrant);
+        }
+        let mut cancel = pin!(cancel);
+        let _guard = Cancellable::new(self.acquire_write(), cancel.as_mut()).await??;
+        let _poison = self.state.poison_guard();
+        let call = holder::hold(self.id, call, arg);
+        match policy {
+            CancelPolicy::OnlyWaiting => Ok(call.await),
+            CancelPolicy::AlsoRunning => Cancellable::new(call, cancel).await,
+        }
+    }
+
+    /// Async read run fn
+    /// return [`Error::Cancelled`](crate::Error::Cancelled) if the cancel signal is ready before the read lock is acquired,
+    /// or before the call finished if the policy is [`CancelPolicy::AlsoRunning`]
+    /// cancel can be any future, example `token.cancelled()` of tokio-util CancellationToken
+    #[inline]
+    pub async fn read_run_with_cancel<'a, A: ?Sized, T, R>(
+        &self, cancel: impl Future, policy: CancelPolicy, call: impl FnOnce(RefInner<'a, A>) -> T, arg: &'a A,
+    ) -> Result<R>
+    where
+        T: Future<Output = R>,
+    {
+        if holder::is_held(self.id) {
+            return Err(Error::Reentrant);
+        }
+        let arg = RefInner { value: arg };
+        let mut cancel = pin!(cancel);
+        let _guard = Cancellable::new(self.acquire_read(), cancel.as_mut()).await??;
+        let call = holder::hold(self.id, call, arg);
+        match policy {
+            CancelPolicy::OnlyWaiting => Ok(call.await),
+            CancelPolicy::AlsoRunning => Cancellable::new(call, cancel).await,
+        }
+    }
+
+    /// Async write run fn, the call run to completion even if the caller is dropped
+    /// once the write lock is acquired, the call is spawned on the runtime, the result is discarded if the caller is dropped
+    /// return [`Error::Reentrant`] instead of deadlock if the current task is already holding this queue
+    /// return [`Error::Poisoned`] if a write call panicked before
+    /// the panic of this call poison the queue and is resumed on the caller
+    /// return [`Error::Cancelled`] if the runtime drop the call without run, like at shutdown
+    /// return [`Error::Closed`] if the queue is closed
+    /// need a [`Runtime`](crate::Runtime), return [`Error::NoRuntime`](crate::Error::NoRuntime) if the queue has no runtime
+    #[inline]
+    pub async fn write_run_detached<A, T, R>(&self, call: impl FnOnce(A) -> T, arg: A) -> Result<R>
+    where
+        T: Future<Output = R> + Send + 'static,
+        R: Send + 'static,
+    {
+        if holder::is_held(self.id) {
+            return Err(Error::Reentrant);
+        }
+        let runtime = runtime::get(self.runtime())?;
+        let guard = self.acquire_write_arc().await?;
+        let future = holder::hold(self.id, call, arg);
+        detach::spawn(runtime, guard, Some(self.state.clone()), future).await
+    }
+
+    /// Async write run blocking fn, for the heavy sync work not stall the async worker
+    /// the call run on the blocking thread pool of the runtime once the lock is acquired, the lock is released when the call return
+    /// the call run to completion even if the caller is dropped, the result is discarded
+    /// return [`Error::Reentrant`] instead of deadlock if the current task is already holding this queue
+    /// return [`Error::Poisoned`] if a write call panicked before
+    /// the panic of this call poison the queue and is resumed on the caller
+    /// return [`Error::Cancelled`] if the runtime drop the call without run, like at shutdown
+    /// return [`Error::Closed`] if the queue is closed
+    /// need a [`Runtime`](crate::Runtime), return [`Error::NoRuntime`](crate::Error::NoRuntime) if the queue has no runtime
+    #[inline]
+    pub async fn write_run_blocking<A, R>(&self, call: impl FnOnce(A) -> R + Send + 'static, arg: A) -> Result<R>
+    where
+        A: Send + 'static,
+        R: Send + 'static,
+    {
+        if holder::is_held(self.id) {
+            return Err(Error::Reentrant);
+        }
+        let runtime = runtime::get(self.runtime())?;
+        let guard = self.acquire_write_arc().await?;
+        let id = self.id;
+        detach::spawn_blocking(runtime, guard, Some(self.state.clone()), move || holder::scope(id, || call(arg))).await
+    }
+
+    /// Async read run blocking fn, for the heavy sync work not stall the async worker
+    /// the call run on the blocking thread pool of the runtime once the lock is acquired, the lock is released when the call return
+    /// the call run to completion even if the caller is dropped, the result is discarded
+    /// return [`Error::Reentrant`] instead of deadlock if the current task is already holding this queue
+    /// return [`Error::Poisoned`] if a write call panicked before
+    /// the panic of this call is resumed on the caller but not poison the queue
+    /// return [`Error::Cancelled`] if the runtime drop the call without run, like at shutdown
+    /// return [`Error::Closed`] if the queue is closed
+    /// need a [`Runtime`](crate::Runtime), return [`Error::NoRuntime`](crate::Error::NoRuntime) if the queue has no runtime
+    #[inline]
+    pub async fn read_run_blocking<A, R>(&self, call: impl FnOnce(A) -> R + Send + 'static, arg: A) -> Result<R>
+    where
+        A: Send + 'static,
+        R: Send + 'static,
+    {
+        if holder::is_held(self.id) {
+            return Err(Error::Reentrant);
+        }
+        let runtime = runtime::get(self.runtime())?;
+        let guard = self.acquire_read_arc().await?;
+        let id = self.id;
+        // the read call not poison the queue
+        detach::spawn_blocking(runtime, guard, None, move || holder::scope(id, || call(arg))).await
+    }
+
+    /// Async write lock, return a guard hold the write lock until drop
+    /// for the critical section not fit in one closure, the panic while holding the guard poison the queue
+    /// the reentrant check not apply to the guard, see [`RwQueueWriteGuard`]
+    /// # Panics
+    /// if the current task is already holding this queue in a call, or the queue is closed or poisoned
+    #[inline]
+    pub async fn write(&self) -> RwQueueWriteGuard<'_> {
+        self.checked_write().await.unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Async write lock, return a guard hold the write lock until drop
+    /// for the critical section not fit in one closure, the panic while holding the guard poison the queue
+    /// the reentrant check not apply to the guard, see [`RwQueueWriteGuard`]
+    /// return [`Error::Reentrant`] instead of deadlock if the current task is already holding this queue in a call
+    /// return [`Error::Poisoned`] if a write call panicked before
+    /// return [`Error::Closed`] if the queue is closed
+    #[inline]
+    pub async fn checked_write(&self) -> Result<RwQueueWriteGuard<'_>> {
+        if holder::is_held(self.id) {
+            return Err(Error::Reentrant);
+        }
+        let guard = self.acquire_write().await?;
+        Ok(RwQueueWriteGuard {
+            _poison: self.state.poison_guard(),
+            _guard: guard,
+        })
+    }
+
+    /// Async write lock, return an owned guard hold the write lock until drop, it can be moved into a spawned task
+    /// the reentrant check not apply to the guard, see [`RwQueueWriteGuard`]
+    /// # Panics
+    /// if the current task is already holding this queue in a call, or the queue is closed or poisoned
+    #[inline]
+    pub async fn write_arc(&self) -> RwQueueWriteGuardArc {
+        self.checked_write_arc().await.unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Async write lock, return an owned guard hold the write lock until drop, it can be moved into a spawned task
+    /// the reentrant check not apply to the guard, see [`RwQueueWriteGuard`]
+    /// return [`Error::Reentrant`] instead of deadlock if the current task is already holding this queue in a call
+    /// return [`Error::Poisoned`] if a write call panicked before
+    /// return [`Error::Closed`] if the queue is closed
+    #[inline]
+    pub async fn checked_write_arc(&self) -> Result<RwQueueWriteGuardArc> {
+        if holder::is_held(self.id) {
+            return Err(Error::Reentrant);
+        }
+        let guard = self.acquire_write_arc().await?;
+        Ok(RwQueueWriteGuardArc {
+            _poison: self.state.poison_guard_arc(),
+            _guard: guard,
+        })
+    }
+
+    /// Async read lock, return a guard hold the read lock until drop
+    /// for the critical section not fit in one closure
+    /// the reentrant check not apply to the guard, see [`RwQueueWriteGuard`]
+    /// # Panics
+    /// if the current task is already holding this queue in a call, or the queue is closed or poisoned
+    #[inline]
+    pub async fn read(&self) -> RwQueueReadGuard<'_> {
+        self.checked_read().await.unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Async read lock, return a guard hold the read lock until drop
+    /// for the critical section not fit in one closure
+    /// the reentrant check not apply to the guard, see [`RwQueueWriteGuard`]
+    /// return [`Error::Reentrant`] instead of deadlock if the current task is already holding this queue in a call
+    /// return [`Error::Poisoned`] if a write call panicked before
+    /// return [`Error::Closed`] if the queue is closed
+    #[inline]
+    pub async fn checked_read(&self) -> Result<RwQueueReadGuard<'_>> {
+        if holder::is_held(self.id) {
+            return Err(Error::Reentrant);
+        }
+        let guard = self.acquire_read().await?;
+        Ok(RwQueueReadGuard { _guard: guard })
+    }
+
+    /// Async read lock, return an owned guard hold the read lock until drop, it can be moved into a spawned task
+    /// the reentrant check not apply to the guard, see [`RwQueueWriteGuard`]
+    /// # Panics
+    /// if the current task is already holding this queue in a call, or the queue is closed or poisoned
+    #[inline]
+    pub async fn read_arc(&self) -> RwQueueReadGuardArc {
+        self.checked_read_arc().await.unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Async read lock, return an owned guard hold the read lock until drop, it can be moved into a spawned task
+    /// the reentrant check not apply to the guard, see [`RwQueueWriteGuard`]
+    /// return [`Error::Reentrant`] instead of deadlock if the current task is already holding this queue in a call
+    /// return [`Error::Poisoned`] if a write call panicked before
+    /// return [`Error::Closed`] if the queue is closed
+    #[inline]
+    pub async fn checked_read_arc(&self) -> Result<RwQueueReadGuardArc> {
+        if holder::is_held(self.id) {
+            return Err(Error::Reentrant);
+        }
+        let guard = self.acquire_read_arc().await?;
+        Ok(RwQueueReadGuardArc { _guard: guard })
+    }
+}
+
+/// guard of [`RwQueue::write`], the write lock is held until drop
+/// the panic while holding the guard poison the queue
+/// # Reentrancy
+/// the reentrant check not apply to the guard, it can move across tasks and threads so the queue can not tell who hold it,
+/// call the queue again on the same task while holding the guard deadlock instead of return [`Error::Reentrant`], drop the guard first
+#[must_use = "the write lock is released at once if the guard is not held"]
+pub struct RwQueueWriteGuard<'a> {
+    // poison before release the lock
+    _poison: PoisonGuard<&'a State>,
+    _guard: WriteGuard<'a>,
+}
+
+/// owned guard of [`RwQueue::write_arc`], the write lock is held until drop
+/// the panic while holding the guard poison the queue
+/// the reentrant check not apply, see [`RwQueueWriteGuard`]
+#[must_use = "the write lock is released at once if the guard is not held"]
+pub struct RwQueueWriteGuardArc {
+    // poison before release the lock
+    _poison: PoisonGuard<Arc<State>>,
+    _guard: WriteGuardArc,
+}
+
+/// guard of [`RwQueue::read`], the read lock is held until drop
+/// the reentrant check not apply, see [`RwQueueWriteGuard`]
+#[must_use = "the read lock is released at once if the guard is not held"]
+pub struct RwQueueReadGuard<'a> {
+    _guard: ReadGuard<'a>,
+}
+
+/// owned guard of [`RwQueue::read_arc`], the read lock is held until drop
+/// the reentrant check not apply, see [`RwQueueWriteGuard`]
+#[must_use = "the read lock is released at once if the guard is not held"]
+pub struct RwQueueReadGuardArc {
+    _guard: ReadGuardArc,
+}
+
+/// RwQueue upgradable read ref
+/// deref to the inner like a read call, upgrade to [`RefMutInner`] without release the lock
+pub struct UpgradableInner<'a, A: ?Sized> {
+    value: NonNull<A>,
+    lock: UpgradableLock,
+    state: Arc<State>,
+    _marker: PhantomData<&'a mut A>,
+}
+
+enum UpgradableLock {
+    Read(UpgradableGuardArc),
+    // keep the future of a cancelled upgrade, so the lock is not released and the next upgrade resume it
+    Upgrading(Pin<Box<dyn Future<Output = WriteGuardArc> + Send>>),
+    // poison before release the lock
+    Write {
+        _poison: PoisonGuard<Arc<State>>,
+        _guard: WriteGuardArc,
+    },
+}
+
+unsafe impl<A: ?Sized + Send> Send for UpgradableInner<'_, A> {}
+unsafe impl<A: ?Sized + Sync> Sync for UpgradableInner<'_, A> {}
+
+impl<A: ?Sized> UpgradableInner<'_, A> {
+    /// wait the read calls finish and upgrade to write, the lock is not released between
+    /// call it again after upgrade return at once, the panic after upgrade poison the queue
+    /// a cancelled upgrade keep the lock, the inner can still be read and the next upgrade resume the wait
+    #[inline]
+    pub async fn upgrade(&mut self) -> RefMutInner<'_, A> {
+        if let UpgradableLock::Read(_) = self.lock {
+            // no await between take the guard and put the future back, the lock always stay in self
+            let UpgradableLock::Read(guard) = std::mem::replace(&mut self.lock, UpgradableLock::Upgrading(Box::pin(std::future::pending()))) else {
+                unreachable!()
+            };
+            self.lock = UpgradableLock::Upgrading(Box::pin(guard.upgrade()));
+        }
+        if let UpgradableLock::Upgrading(upgrade) = &mut self.lock {
+            let guard = upgrade.as_mut().await;
+            self.lock = UpgradableLock::Write {
+                _poison: self.state.poison_guard_arc(),
+                _guard: guard,
+            };
+        }
+        // Safety: the write lock is held, the readers are gone
+        RefMutInner::new(unsafe { self.value.as_mut() })
+    }
+
+    /// is it upgraded to write
+    #[inline]
+    pub fn is_upgraded(&self) -> bool {
+        matches!(self.lock, UpgradableLock::Write { .. })
+    }
+}
+
+impl<A: ?Sized> Deref for UpgradableInner<'_, A> {
+    type Target = A;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { self.value.as_ref() }
     }
 }
@@ -0,0 +1,317 @@
+use async_lock::{
+    Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockReadGuardArc, RwLockUpgradableReadGuardArc, RwLockWriteGuard, RwLockWriteGuardArc,
+};
+use event_listener::{Event, Listener};
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// who go first when the readers and writers wait for the RwQueue at the same time
+/// it is honored by the async, sync, blocking and try calls
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RwPolicy {
+    /// a waiting writer stop the later readers, for write heavy queues, the readers may wait long
+    #[default]
+    WriterPreferring,
+    /// the readers go first while the lock is shared, for read mostly queues, the writers may wait long
+    /// Note: the upgrade of upgradable read also wait the readers leave
+    ReaderPreferring,
+    /// first come, first run, the readers in a row share the lock, a reader behind a waiting writer wait it
+    Fifo,
+}
+
+/// the rwlock of RwQueue, acquire it in the order of the policy
+pub(crate) struct PolicyLock {
+    policy: RwPolicy,
+    lock: Arc<RwLock<()>>,
+    /// Fifo: all the callers pass it in order, ReaderPreferring: the writers wait in order
+    gate: Mutex<()>,
+    /// ReaderPreferring: the lock is released, wake the waiting writer
+    released: Arc<Event>,
+}
+
+pub(crate) struct ReadGuard<'a> {
+    _guard: RwLockReadGuard<'a, ()>,
+    _released: Released<&'a Event>,
+}
+
+pub(crate) struct WriteGuard<'a> {
+    _guard: RwLockWriteGuard<'a, ()>,
+    _released: Released<&'a Event>,
+}
+
+pub(crate) struct ReadGuardArc {
+    _guard: RwLockReadGuardArc<()>,
+    _released: Released<Arc<Event>>,
+}
+
+pub(crate) struct WriteGuardArc {
+    _guard: RwLockWriteGuardArc<()>,
+    _released: Released<Arc<Event>>,
+}
+
+pub(crate) struct UpgradableGuardArc {
+    guard: RwLockUpgradableReadGuardArc<()>,
+    released: Released<Arc<Event>>,
+}
+
+/// notify the waiting writers after the lock is released, only for ReaderPreferring
+struct Released<E: Deref<Target = Event>>(Option<E>);
+
+impl<E: Deref<Target = Event>> Drop for Released<E> {
+    #[inline]
+    fn drop(&mut self) {
+        if let Some(event) = &self.0 {
+            event.notify(usize::MAX);
+        }
+    }
+}
+
+impl PolicyLock {
+    #[inline]
+    pub(crate) fn new(policy: RwPolicy) -> PolicyLock {
+        PolicyLock {
+            policy,
+            lock: Arc::new(RwLock::new(())),
+            gate: Mutex::new(()),
+            released: Arc::new(Event::new()),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn policy(&self) -> RwPolicy {
+        self.policy
+    }
+
+    /// the caller need pass the gate before the lock
+    #[inline]
+    fn gated(&self, write: bool) -> bool {
+        match self.policy {
+            RwPolicy::WriterPreferring => false,
+            RwPolicy::ReaderPreferring => write,
+            RwPolicy::Fifo => true,
+        }
+    }
+
+    #[inline]
+    async fn enter(&self, write: bool) -> Option<MutexGuard<'_, ()>> {
+        if self.gated(write) {
+            Some(self.gate.lock().await)
+        } else {
+            None
+        }
+    }
+
+    /// return None if the gate is held by others
+    #[inline]
+    fn try_enter(&self, write: bool) -> Option<Option<MutexGuard<'_, ()>>> {
+        if self.gated(write) {
+            self.gate.try_lock().map(Some)
+        } else {
+            Some(None)
+        }
+    }
+
+    #[inline]
+    fn enter_blocking(&self, write: bool) -> Option<MutexGuard<'_, ()>> {
+        if self.gated(write) {
+            Some(self.gate.lock_blocking())
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    fn is_reader_preferring(&self) -> bool {
+        self.policy == RwPolicy::ReaderPreferring
+    }
+
+    #[inline]
+    fn released(&self) -> Released<&Event> {
+        Released(self.is_reader_preferring().then_some(&*self.released))
+    }
+
+    #[inline]
+    fn released_arc(&self) -> Released<Arc<Event>> {
+        Released(self.is_reader_preferring().then(|| self.released.clone()))
+    }
+
+    /// the writer of ReaderPreferring never stop the readers, try again after the lock is released
+    #[inline]
+    async fn wait_released<G>(&self, mut try_acquire: impl FnMut() -> Option<G>) -> G {
+        loop {
+            if let Some(guard) = try_acquire() {
+                return guard;
+            }
+            let listener = self.released.listen();
+            if let Some(guard) = try_acquire() {
+                return guard;
+            }
+            listener.await;
+        }
+    }
+
+    #[inline]
+    fn wait_released_blocking<G>(&self, mut try_acquire: impl FnMut() -> Option<G>) -> G {
+        loop {
+            if let Some(guard) = try_acquire() {
+                return guard;
+            }
+            let listener = self.released.listen();
+            if let Some(guard) = try_acquire() {
+                return guard;
+            }
+            listener.wait();
+        }
+    }
+
+    #[inline]
+    pub(crate) async fn read(&self) -> ReadGuard<'_> {
+        let _gate = self.enter(false).await;
+        ReadGuard {
+            _guard: self.lock.read().await,
+            _released: self.released(),
+        }
+    }
+
+    #[inline]
+    pub(crate) async fn write(&self) -> WriteGuard<'_> {
+        let _gate = self.enter(true).await;
+        let guard = if self.is_reader_preferring() {
+            self.wait_released(|| self.lock.try_write()).await
+        } else {
+            self.lock.write().await
+        };
+        WriteGuard {
+            _guard: guard,
+            _released: self.released(),
+        }
+    }
+
+    #[inline]
+    pub(crate) async fn read_arc(&self) -> ReadGuardArc {
+        let _gate = self.enter(false).await;
+        ReadGuardArc {
+            _guard: self.lock.read_arc().await,
+            _released: self.released_arc(),
+        }
+    }
+
+    #[inline]
+    pub(crate) async fn write_arc(&self) -> WriteGuardArc {
+        let _gate = self.enter(true).await;
+        let guard = if self.is_reader_preferring() {
+            self.wait_released(|| self.lock.try_write_arc()).await
+        } else {
+            self.lock.write_arc().await
+        };
+        WriteGuardArc {
+            _guard: guard,
+            _released: self.released_arc(),
+        }
+    }
+
+    #[inline]
+    pub(crate) async fn upgradable_read_arc(&self) -> UpgradableGuardArc {
+        let _gate = self.enter(false).await;
+        UpgradableGuardArc {
+            guard: self.lock.upgradable_read_arc().await,
+            released: self.released_arc(),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn try_read(&self) -> Option<ReadGuard<'_>> {
+        let _gate = self.try_enter(false)?;
+        Some(ReadGuard {
+            _guard: self.lock.try_read()?,
+            _released: self.released(),
+        })
+    }
+
+    #[inline]
+    pub(crate) fn try_write(&self) -> Option<WriteGuard<'_>> {
+        let _gate = self.try_enter(true)?;
+        Some(WriteGuard {
+            _guard: self.lock.try_write()?,
+            _released: self.released(),
+        })
+    }
+
+    #[inline]
+    pub(crate) fn try_read_arc(&self) -> Option<ReadGuardArc> {
+        let _gate = self.try_enter(false)?;
+        Some(ReadGuardArc {
+            _guard: self.lock.try_read_arc()?,
+            _released: self.released_arc(),
+        })
+    }
+
+    #[inline]
+    pub(crate) fn try_write_arc(&self) -> Option<WriteGuardArc> {
+        let _gate = self.try_enter(true)?;
+        Some(WriteGuardArc {
+            _guard: self.lock.try_write_arc()?,
+            _released: self.released_arc(),
+        })
+    }
+
+    #[inline]
+    pub(crate) fn try_upgradable_read_arc(&self) -> Option<UpgradableGuardArc> {
+        let _gate = self.try_enter(false)?;
+        Some(UpgradableGuardArc {
+            guard: self.lock.try_upgradable_read_arc()?,
+            released: self.released_arc(),
+        })
+    }
+
+    #[inline]
+    pub(crate) fn read_blocking(&self) -> ReadGuard<'_> {
+        let _gate = self.enter_blocking(false);
+        ReadGuard {
+            _guard: self.lock.read_blocking(),
+            _released: self.released(),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn write_blocking(&self) -> WriteGuard<'_> {
+        let _gate = self.enter_blocking(true);
+        let guard = if self.is_reader_preferring() {
+            self.wait_released_blocking(|| self.lock.try_write())
+        } else {
+            self.lock.write_blocking()
+        };
+        WriteGuard {
+            _guard: guard,
+            _released: self.released(),
+        }
+    }
+}
+
+impl UpgradableGuardArc {
+    /// upgrade to write without release the lock
+    /// ReaderPreferring wait the readers leave instead of stop the later readers
+    #[inline]
+    pub(crate) async fn upgrade(self) -> WriteGuardArc {
+        let UpgradableGuardArc { mut guard, released } = self;
+        let guard = match &released.0 {
+            Some(event) => loop {
+                guard = match RwLockUpgradableReadGuardArc::try_upgrade(guard) {
+                    Ok(guard) => break guard,
+                    Err(guard) => guard,
+                };
+                let listener = event.listen();
+                guard = match RwLockUpgradableReadGuardArc::try_upgrade(guard) {
+                    Ok(guard) => break guard,
+                    Err(guard) => guard,
+                };
+                listener.await;
+            },
+            None => RwLockUpgradableReadGuardArc::upgrade(guard).await,
+        };
+        WriteGuardArc {
+            _guard: guard,
+            _released: released,
+        }
+    }
+}
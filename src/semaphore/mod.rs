@@ -1,34 +1,308 @@
-use async_lock::Semaphore;
+use crate::blocking;
+use crate::cancel::{CancelPolicy, Cancellable};
+use crate::detach;
+use crate::error::{Error, Result};
+use crate::runtime::{self, Runtime};
+use crate::state::{FullPolicy, PoisonGuard, State};
+use crate::time::IntoDeadline;
+use async_lock::{Semaphore, SemaphoreGuard, SemaphoreGuardArc};
 use std::future::Future;
+use std::panic::{RefUnwindSafe, UnwindSafe};
+use std::pin::pin;
+use std::sync::Arc;
 
 /// Used to control task parallelism queue
 pub struct SemaphoreQueue {
-    semaphore: Semaphore,
+    state: Arc<State>,
+    runtime: Option<Arc<dyn Runtime>>,
+    semaphore: Arc<Semaphore>,
 }
 
 impl Default for SemaphoreQueue {
     #[inline]
     fn default() -> Self {
-        SemaphoreQueue {
-            semaphore: Semaphore::new(5),
-        }
+        SemaphoreQueue::new(5)
     }
 }
 
+/// the panic of call poison the queue
+impl UnwindSafe for SemaphoreQueue {}
+impl RefUnwindSafe for SemaphoreQueue {}
+
 impl SemaphoreQueue {
     #[inline]
     pub fn new(n: usize) -> SemaphoreQueue {
         SemaphoreQueue {
-            semaphore: Semaphore::new(n),
+            state: Arc::default(),
+            runtime: None,
+            semaphore: Arc::new(Semaphore::new(n)),
+        }
+    }
+
+    /// Create a queue use the runtime for timeout
+    #[inline]
+    pub fn with_runtime(n: usize, runtime: impl Runtime) -> SemaphoreQueue {
+        SemaphoreQueue {
+            runtime: Some(Arc::new(runtime)),
+            ..SemaphoreQueue::new(n)
+        }
+    }
+
+    /// Create a bounded queue, at most max_pending callers wait for the permit, the holders of the permits are not counted
+    /// the later calls return [`Error::Full`](crate::Error::Full) or wait for space by the policy, with max_pending 0 the call only run if a permit is free now
+    /// # Panics
+    /// if max_pending is 0 and the policy is [`FullPolicy::Wait`](crate::FullPolicy::Wait)
+    #[inline]
+    pub fn bounded(n: usize, max_pending: usize, policy: FullPolicy) -> SemaphoreQueue {
+        SemaphoreQueue {
+            state: Arc::new(State::bounded(max_pending, policy)),
+            ..SemaphoreQueue::new(n)
         }
     }
 
+    /// the runtime of timeout, set by with_runtime or the default runtime of the enabled features
+    #[inline]
+    pub fn runtime(&self) -> Option<&dyn Runtime> {
+        runtime::or_default(&self.runtime)
+    }
+
+    /// close the queue, the waiting and later calls return [`Error::Closed`](crate::Error::Closed), the running call is not affected
+    #[inline]
+    pub fn close(&self) {
+        self.state.close()
+    }
+
+    /// is the queue closed
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        self.state.is_closed()
+    }
+
+    /// a call panicked while holding the permit
+    #[inline]
+    pub fn is_poisoned(&self) -> bool {
+        self.state.is_poisoned()
+    }
+
+    /// clear the poisoned state, make sure the state is consistent before call it
+    #[inline]
+    pub fn clear_poison(&self) {
+        self.state.clear_poison()
+    }
+
+    /// wait the permit, the caller is pending until the permit is acquired
+    #[inline]
+    async fn acquire_permit(&self) -> Result<SemaphoreGuard<'_>> {
+        self.state.enter(|| self.semaphore.try_acquire(), || self.semaphore.acquire()).await
+    }
+
+    /// wait the owned permit, the caller is pending until the permit is acquired
+    #[inline]
+    async fn acquire_permit_arc(&self) -> Result<SemaphoreGuardArc> {
+        self.state
+            .enter(|| self.semaphore.try_acquire_arc(), || self.semaphore.acquire_arc())
+            .await
+    }
+
+    /// # Panics
+    /// if the queue is closed or poisoned
     #[inline]
     pub async fn run<A, T, R>(&self, call: impl FnOnce(A) -> T, arg: A) -> R
     where
         T: Future<Output = R>,
     {
-        let _guard = self.semaphore.acquire().await;
-        call(arg).await
+        self.checked_run(call, arg).await.unwrap_or_else(|err| panic!("{}", err))
     }
+
+    /// return [`Error::Poisoned`](crate::Error::Poisoned) if a call panicked before, the panic of call poison the queue
+    /// return [`Error::Closed`](crate::Error::Closed) if the queue is closed
+    #[inline]
+    pub async fn checked_run<A, T, R>(&self, call: impl FnOnce(A) -> T, arg: A) -> Result<R>
+    where
+        T: Future<Output = R>,
+    {
+        let _guard = self.acquire_permit().await?;
+        let _poison = self.state.poison_guard();
+        Ok(call(arg).await)
+    }
+
+    /// Sync run fn
+    /// spin briefly, then park the thread and wait in first in, first run order with the async callers
+    /// # Panics
+    /// if it need wait on an async worker thread, or the queue is closed or poisoned
+    #[inline]
+    pub fn sync_run<A, R>(&self, call: impl FnOnce(A) -> R, arg: A) -> R {
+        self.checked_sync_run(call, arg).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Sync run fn
+    /// spin briefly, then park the thread and wait in first in, first run order with the async callers
+    /// return [`Error::AsyncWorker`](crate::Error::AsyncWorker) if it need wait on an async worker thread, park it will stall the executor
+    /// return [`Error::Poisoned`](crate::Error::Poisoned) if a call panicked before, the panic of call poison the queue
+    /// return [`Error::Closed`](crate::Error::Closed) if the queue is closed
+    #[inline]
+    pub fn checked_sync_run<A, R>(&self, call: impl FnOnce(A) -> R, arg: A) -> Result<R> {
+        let _guard = self.state.sync_enter(
+            || self.semaphore.try_acquire(),
+            || blocking::acquire(|| self.semaphore.try_acquire(), || self.semaphore.acquire_blocking()),
+        )?;
+        let _poison = self.state.poison_guard();
+        Ok(call(arg))
+    }
+
+    /// Blocking run async fn
+    /// block the current thread until the call finished, the async fn is run on the current thread
+    /// it wait in first in, first run order with the async callers, for plain threads like FFI callbacks, rayon workers or Drop
+    /// # Panics
+    /// if it is called on an async worker thread, or the queue is closed or poisoned
+    #[inline]
+    pub fn block_run<A, T, R>(&self, call: impl FnOnce(A) -> T, arg: A) -> R
+    where
+        T: Future<Output = R>,
+    {
+        self.checked_block_run(call, arg).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Blocking run async fn
+    /// block the current thread until the call finished, the async fn is run on the current thread
+    /// it wait in first in, first run order with the async callers, for plain threads like FFI callbacks, rayon workers or Drop
+    /// return [`Error::AsyncWorker`](crate::Error::AsyncWorker) if it is called on an async worker thread, block it will stall the executor
+    /// return [`Error::Poisoned`](crate::Error::Poisoned) if a call panicked before, the panic of call poison the queue
+    /// return [`Error::Closed`](crate::Error::Closed) if the queue is closed
+    #[inline]
+    pub fn checked_block_run<A, T, R>(&self, call: impl FnOnce(A) -> T, arg: A) -> Result<R>
+    where
+        T: Future<Output = R>,
+    {
+        if blocking::in_async_worker() {
+            return Err(Error::AsyncWorker);
+        }
+        blocking::block_on(self.checked_run(call, arg))
+    }
+
+    /// run only if a permit is free now
+    /// return [`Error::WouldBlock`](crate::Error::WouldBlock) at once if no permit
+    /// return [`Error::Poisoned`](crate::Error::Poisoned) if a call panicked before, the panic of call poison the queue
+    /// return [`Error::Closed`](crate::Error::Closed) if the queue is closed
+    #[inline]
+    pub async fn try_run<A, T, R>(&self, call: impl FnOnce(A) -> T, arg: A) -> Result<R>
+    where
+        T: Future<Output = R>,
+    {
+        self.state.check_closed()?;
+        let _guard = self.semaphore.try_acquire().ok_or(Error::WouldBlock)?;
+        self.state.check()?;
+        let _poison = self.state.poison_guard();
+        Ok(call(arg).await)
+    }
+
+    /// return [`Error::Timeout`](crate::Error::Timeout) if the permit is not acquired before the timeout
+    /// once acquired the call always run to completion, the running time is not limited
+    /// timeout can be a [`Duration`](std::time::Duration) or a deadline [`Instant`](std::time::Instant)
+    /// need a [`Runtime`](crate::Runtime), return [`Error::NoRuntime`](crate::Error::NoRuntime) if the queue has no runtime
+    #[inline]
+    pub async fn run_acquire_timeout<A, T, R>(&self, timeout: impl IntoDeadline, call: impl FnOnce(A) -> T, arg: A) -> Result<R>
+    where
+        T: Future<Output = R>,
+    {
+        let _guard = runtime::get(self.runtime())?.timeout(timeout, self.acquire_permit()).await??;
+        let _poison = self.state.poison_guard();
+        Ok(call(arg).await)
+    }
+
+    /// return [`Error::Cancelled`](crate::Error::Cancelled) if the cancel signal is ready before the permit is acquired,
+    /// or before the call finished if the policy is [`CancelPolicy::AlsoRunning`]
+    /// cancel can be any future, example `token.cancelled()` of tokio-util CancellationToken
+    #[inline]
+    pub async fn run_with_cancel<A, T, R>(&self, cancel: impl Future, policy: CancelPolicy, call: impl FnOnce(A) -> T, arg: A) -> Result<R>
+    where
+        T: Future<Output = R>,
+    {
+        let mut cancel = pin!(cancel);
+        let _guard = Cancellable::new(self.acquire_permit(), cancel.as_mut()).await??;
+        let _poison = self.state.poison_guard();
+        match policy {
+            CancelPolicy::OnlyWaiting => Ok(call(arg).await),
+            CancelPolicy::AlsoRunning => Cancellable::new(call(arg), cancel).await,
+        }
+    }
+
+    /// Async run blocking fn, for the heavy sync work not stall the async worker
+    /// the call run on the blocking thread pool of the runtime once the permit is acquired, the permit is released when the call return
+    /// the call run to completion even if the caller is dropped, the result is discarded
+    /// return [`Error::Poisoned`](crate::Error::Poisoned) if a call panicked before
+    /// the panic of this call poison the queue and is resumed on the caller
+    /// return [`Error::Cancelled`](crate::Error::Cancelled) if the runtime drop the call without run, like at shutdown
+    /// return [`Error::Closed`](crate::Error::Closed) if the queue is closed
+    /// need a [`Runtime`](crate::Runtime), return [`Error::NoRuntime`](crate::Error::NoRuntime) if the queue has no runtime
+    #[inline]
+    pub async fn run_blocking<A, R>(&self, call: impl FnOnce(A) -> R + Send + 'static, arg: A) -> Result<R>
+    where
+        A: Send + 'static,
+        R: Send + 'static,
+    {
+        let runtime = runtime::get(self.runtime())?;
+        let guard = self.acquire_permit_arc().await?;
+        detach::spawn_blocking(runtime, guard, Some(self.state.clone()), move || call(arg)).await
+    }
+
+    /// Async acquire a permit, return a guard hold the permit until drop
+    /// for the critical section not fit in one closure, the panic while holding the guard poison the queue
+    /// # Panics
+    /// if the queue is closed or poisoned
+    #[inline]
+    pub async fn acquire(&self) -> SemaphoreQueueGuard<'_> {
+        self.checked_acquire().await.unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Async acquire a permit, return a guard hold the permit until drop
+    /// for the critical section not fit in one closure, the panic while holding the guard poison the queue
+    /// return [`Error::Poisoned`](crate::Error::Poisoned) if a call panicked before
+    /// return [`Error::Closed`](crate::Error::Closed) if the queue is closed
+    #[inline]
+    pub async fn checked_acquire(&self) -> Result<SemaphoreQueueGuard<'_>> {
+        let guard = self.acquire_permit().await?;
+        Ok(SemaphoreQueueGuard {
+            _poison: self.state.poison_guard(),
+            _guard: guard,
+        })
+    }
+
+    /// Async acquire a permit, return an owned guard hold the permit until drop, it can be moved into a spawned task
+    /// # Panics
+    /// if the queue is closed or poisoned
+    #[inline]
+    pub async fn acquire_arc(&self) -> SemaphoreQueueGuardArc {
+        self.checked_acquire_arc().await.unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Async acquire a permit, return an owned guard hold the permit until drop, it can be moved into a spawned task
+    /// return [`Error::Poisoned`](crate::Error::Poisoned) if a call panicked before
+    /// return [`Error::Closed`](crate::Error::Closed) if the queue is closed
+    #[inline]
+    pub async fn checked_acquire_arc(&self) -> Result<SemaphoreQueueGuardArc> {
+        let guard = self.acquire_permit_arc().await?;
+        Ok(SemaphoreQueueGuardArc {
+            _poison: self.state.poison_guard_arc(),
+            _guard: guard,
+        })
+    }
+}
+
+/// guard of [`SemaphoreQueue::acquire`], the permit is held until drop
+/// the panic while holding the guard poison the queue
+#[must_use = "the permit is released at once if the guard is not held"]
+pub struct SemaphoreQueueGuard<'a> {
+    // poison before release the permit
+    _poison: PoisonGuard<&'a State>,
+    _guard: SemaphoreGuard<'a>,
+}
+
+/// owned guard of [`SemaphoreQueue::acquire_arc`], the permit is held until drop
+/// the panic while holding the guard poison the queue
+#[must_use = "the permit is released at once if the guard is not held"]
+pub struct SemaphoreQueueGuardArc {
+    // poison before release the permit
+    _poison: PoisonGuard<Arc<State>>,
+    _guard: SemaphoreGuardArc,
 }
@@ -0,0 +1,155 @@
+use crate::error::Result;
+use crate::runtime::Runtime;
+use crate::rw_model::RefMutInner;
+use crate::state::FullPolicy;
+use crate::AQueue;
+use arc_swap::ArcSwap;
+use std::panic::{RefUnwindSafe, UnwindSafe};
+use std::sync::Arc;
+
+/// Snapshot Model
+/// Ensure Thread safety and lock free reading, for read mostly state like routing tables and feature flags
+/// the readers get an `Arc` snapshot of the current state without lock,
+/// the writers run through the queue one by one, change a copy of the state and publish it at once
+/// ```
+/// use aqueue::SnapshotModel;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let routes = SnapshotModel::new(vec!["a".to_string()]);
+///     let old = routes.snapshot();
+///     routes.call_mut(async move |mut inner| inner.push("b".to_string())).await;
+///     // the old snapshot is not changed
+///     assert_eq!(old.len(), 1);
+///     assert_eq!(routes.call(async move |inner| inner.len()).await, 2);
+/// }
+/// ```
+pub struct SnapshotModel<I> {
+    queue: AQueue,
+    inner: ArcSwap<I>,
+}
+
+impl<I: Default> Default for SnapshotModel<I> {
+    fn default() -> Self {
+        Self {
+            queue: AQueue::new(),
+            inner: ArcSwap::from_pointee(Default::default()),
+        }
+    }
+}
+
+/// the panic of write call poison the model, the snapshot is not changed by it
+impl<I> UnwindSafe for SnapshotModel<I> {}
+impl<I> RefUnwindSafe for SnapshotModel<I> {}
+
+impl<I: 'static> SnapshotModel<I> {
+    #[inline]
+    pub fn new(x: I) -> SnapshotModel<I> {
+        SnapshotModel {
+            queue: AQueue::new(),
+            inner: ArcSwap::from_pointee(x),
+        }
+    }
+
+    /// Create a model use the runtime for timeout
+    #[inline]
+    pub fn with_runtime(x: I, runtime: impl Runtime) -> SnapshotModel<I> {
+        SnapshotModel {
+            queue: AQueue::with_runtime(runtime),
+            inner: ArcSwap::from_pointee(x),
+        }
+    }
+
+    /// Create a bounded model, at most max_pending write callers wait for the model, the running write call is not counted
+    /// the later write calls return [`Error::Full`](crate::Error::Full) or wait for space by the policy, with max_pending 0 the call only run if no write call is running now
+    /// # Panics
+    /// if max_pending is 0 and the policy is [`FullPolicy::Wait`](crate::FullPolicy::Wait)
+    #[inline]
+    pub fn bounded(x: I, max_pending: usize, policy: FullPolicy) -> SnapshotModel<I> {
+        SnapshotModel {
+            queue: AQueue::bounded(max_pending, policy),
+            inner: ArcSwap::from_pointee(x),
+        }
+    }
+
+    /// a write call panicked while holding the model
+    #[inline]
+    pub fn is_poisoned(&self) -> bool {
+        self.queue.is_poisoned()
+    }
+
+    /// clear the poisoned state, the snapshot is not changed by the panicked call
+    #[inline]
+    pub fn clear_poison(&self) {
+        self.queue.clear_poison()
+    }
+
+    /// close the model, the waiting and later write calls return [`Error::Closed`](crate::Error::Closed), the reads are not affected
+    #[inline]
+    pub fn close(&self) {
+        self.queue.close()
+    }
+
+    /// is the model closed
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        self.queue.is_closed()
+    }
+
+    /// the current snapshot without lock, it can be kept and not see the later write calls
+    #[inline]
+    pub fn snapshot(&self) -> Arc<I> {
+        self.inner.load_full()
+    }
+
+    /// Behavior without lock,thread safe call async fn read the current snapshot
+    /// it never wait the write calls, the snapshot is not changed during the call
+    #[inline]
+    pub async fn call<R>(&self, call: impl AsyncFnOnce(Arc<I>) -> R) -> R {
+        call(self.snapshot()).await
+    }
+
+    /// Behavior without lock,thread safe call fn read the current snapshot
+    /// it never wait or block the thread, can be called on any thread
+    #[inline]
+    pub fn sync_call<R>(&self, call: impl FnOnce(&I) -> R) -> R {
+        call(&self.inner.load())
+    }
+}
+
+impl<I: Clone + 'static> SnapshotModel<I> {
+    /// Behavior through queues,thread safe call async fn write a copy of the current state
+    /// the copy is published at once after the call finished, the readers see the old or the new state, never a half write
+    /// the ref mut can not return or store out of the call
+    /// ```compile_fail
+    /// # async fn leak(model: &aqueue::SnapshotModel<u32>) {
+    /// let inner = model.call_mut(async |inner| inner).await;
+    /// # }
+    /// ```
+    /// # Panics
+    /// if call it again inside the write call of this model, or the model is closed or poisoned
+    #[inline]
+    pub async fn call_mut<R>(&self, call: impl AsyncFnOnce(RefMutInner<'_, I>) -> R) -> R {
+        self.checked_call_mut(call).await.unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Behavior through queues,thread safe call async fn write a copy of the current state
+    /// the copy is published at once after the call finished, the panic of call publish nothing and poison the model
+    /// return [`Error::Reentrant`](crate::Error::Reentrant) instead of deadlock if call it again inside the write call of this model
+    /// return [`Error::Poisoned`](crate::Error::Poisoned) if a write call panicked before
+    /// return [`Error::Closed`](crate::Error::Closed) if the model is closed
+    #[inline]
+    pub async fn checked_call_mut<R>(&self, call: impl AsyncFnOnce(RefMutInner<'_, I>) -> R) -> Result<R> {
+        self.queue
+            .checked_run(
+                |inner| async move {
+                    let mut value = I::clone(&inner.load());
+                    let output = call(RefMutInner::new(&mut value)).await;
+                    inner.store(Arc::new(value));
+                    output
+                },
+                &self.inner,
+            )
+            .await
+    }
+}
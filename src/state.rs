@@ -0,0 +1,224 @@
+use crate::blocking;
+use crate::error::{Error, Result};
+use async_lock::{Semaphore, SemaphoreGuard};
+use event_listener::{Event, EventListener};
+use std::future::Future;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicU64, AtomicU8, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+/// what the call do when the bounded queue is full
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FullPolicy {
+    /// return [`Error::Full`] at once
+    #[default]
+    Reject,
+    /// wait for space
+    Wait,
+}
+
+/// max pending callers of queue
+struct Bound {
+    pending: Semaphore,
+    policy: FullPolicy,
+}
+
+const CLOSED: u8 = 1;
+const POISONED: u8 = 2;
+
+/// closed and poisoned flag of queue
+#[derive(Default)]
+pub(crate) struct State {
+    /// closed and poisoned bits, checked in one load on every call
+    flags: AtomicU8,
+    bound: Option<Bound>,
+    /// count of the finished write calls
+    version: AtomicU64,
+    /// the version is changed or the queue is closed
+    changed: Event,
+    /// count of the living subscribers, the write call notify only if someone subscribe
+    subscribers: AtomicUsize,
+}
+
+impl State {
+    /// the running holders are not counted in max_pending
+    /// # Panics
+    /// if max_pending is 0 and the policy is [`FullPolicy::Wait`], the contended callers would wait forever
+    #[inline]
+    pub(crate) fn bounded(max_pending: usize, policy: FullPolicy) -> State {
+        assert!(
+            max_pending > 0 || policy == FullPolicy::Reject,
+            "aqueue max_pending is 0 with FullPolicy::Wait, the contended callers would wait forever"
+        );
+        State {
+            bound: Some(Bound {
+                pending: Semaphore::new(max_pending),
+                policy,
+            }),
+            ..State::default()
+        }
+    }
+
+    /// wait the lock as a pending caller, return the guard if the queue is not closed or poisoned
+    /// try the lock first, only the contended caller enter the pending callers
+    #[inline]
+    pub(crate) async fn enter<G, F>(&self, try_acquire: impl FnOnce() -> Option<G>, acquire: impl FnOnce() -> F) -> Result<G>
+    where
+        F: Future<Output = G>,
+    {
+        let guard = match try_acquire() {
+            Some(guard) => guard,
+            None => {
+                self.check_closed()?;
+                let pending = self.admit().await?;
+                let guard = acquire().await;
+                drop(pending);
+                guard
+            }
+        };
+        self.check()?;
+        Ok(guard)
+    }
+
+    /// wait the lock as a pending caller of sync call, return the guard if the queue is not closed or poisoned
+    /// try the lock first, only the contended caller enter the pending callers
+    #[inline]
+    pub(crate) fn sync_enter<G>(&self, try_acquire: impl FnOnce() -> Option<G>, acquire: impl FnOnce() -> Result<G>) -> Result<G> {
+        let guard = match try_acquire() {
+            Some(guard) => guard,
+            None => {
+                self.check_closed()?;
+                let pending = self.sync_admit()?;
+                let guard = acquire()?;
+                drop(pending);
+                guard
+            }
+        };
+        self.check()?;
+        Ok(guard)
+    }
+
+    /// enter the pending callers, hold the guard until the queue is acquired
+    /// return [`Error::Full`] if full and the policy is [`FullPolicy::Reject`]
+    #[inline]
+    async fn admit(&self) -> Result<Option<SemaphoreGuard<'_>>> {
+        match &self.bound {
+            None => Ok(None),
+            Some(bound) => match bound.policy {
+                FullPolicy::Reject => bound.pending.try_acquire().map(Some).ok_or(Error::Full),
+                FullPolicy::Wait => Ok(Some(bound.pending.acquire().await)),
+            },
+        }
+    }
+
+    /// enter the pending callers of sync call
+    /// return [`Error::Full`] if full, or park the thread if the policy is [`FullPolicy::Wait`]
+    /// return [`Error::AsyncWorker`] if it need park an async worker thread
+    #[inline]
+    fn sync_admit(&self) -> Result<Option<SemaphoreGuard<'_>>> {
+        match &self.bound {
+            None => Ok(None),
+            Some(bound) => match bound.policy {
+                FullPolicy::Reject => bound.pending.try_acquire().map(Some).ok_or(Error::Full),
+                FullPolicy::Wait => blocking::acquire(|| bound.pending.try_acquire(), || bound.pending.acquire_blocking()).map(Some),
+            },
+        }
+    }
+
+    #[inline]
+    pub(crate) fn is_closed(&self) -> bool {
+        self.flags.load(Ordering::Acquire) & CLOSED != 0
+    }
+
+    #[inline]
+    pub(crate) fn close(&self) {
+        self.flags.fetch_or(CLOSED, Ordering::AcqRel);
+        self.changed.notify(usize::MAX);
+    }
+
+    #[inline]
+    pub(crate) fn is_poisoned(&self) -> bool {
+        self.flags.load(Ordering::Acquire) & POISONED != 0
+    }
+
+    #[inline]
+    pub(crate) fn clear_poison(&self) {
+        self.flags.fetch_and(!POISONED, Ordering::AcqRel);
+    }
+
+    #[inline]
+    pub(crate) fn version(&self) -> u64 {
+        self.version.load(Ordering::Acquire)
+    }
+
+    /// add a subscriber, return the version it has seen
+    #[inline]
+    pub(crate) fn subscribe(&self) -> u64 {
+        // SeqCst pair with the change of version, the later change always see the subscriber
+        self.subscribers.fetch_add(1, Ordering::SeqCst);
+        self.version.load(Ordering::SeqCst)
+    }
+
+    #[inline]
+    pub(crate) fn unsubscribe(&self) {
+        self.subscribers.fetch_sub(1, Ordering::Release);
+    }
+
+    /// listen the next change of version or close
+    #[inline]
+    pub(crate) fn listen(&self) -> EventListener {
+        self.changed.listen()
+    }
+
+    /// return [`Error::Closed`] if closed
+    #[inline]
+    pub(crate) fn check_closed(&self) -> Result<()> {
+        if self.is_closed() {
+            Err(Error::Closed)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// return [`Error::Closed`] if closed, [`Error::Poisoned`] if poisoned
+    #[inline]
+    pub(crate) fn check(&self) -> Result<()> {
+        let flags = self.flags.load(Ordering::Acquire);
+        if flags & CLOSED != 0 {
+            Err(Error::Closed)
+        } else if flags & POISONED != 0 {
+            Err(Error::Poisoned)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// poison it if the guard drop while panicking, else the write call is finished, change the version
+    #[inline]
+    pub(crate) fn poison_guard(&self) -> PoisonGuard<&State> {
+        PoisonGuard(self)
+    }
+
+    /// poison it if the owned guard drop while panicking, else change the version
+    #[inline]
+    pub(crate) fn poison_guard_arc(self: &Arc<Self>) -> PoisonGuard<Arc<State>> {
+        PoisonGuard(self.clone())
+    }
+}
+
+pub(crate) struct PoisonGuard<S: Deref<Target = State>>(S);
+
+impl<S: Deref<Target = State>> Drop for PoisonGuard<S> {
+    #[inline]
+    fn drop(&mut self) {
+        if thread::panicking() {
+            self.0.flags.fetch_or(POISONED, Ordering::AcqRel);
+        } else {
+            self.0.version.fetch_add(1, Ordering::SeqCst);
+            if self.0.subscribers.load(Ordering::SeqCst) > 0 {
+                self.0.changed.notify(usize::MAX);
+            }
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use crate::error::{Error, Result};
+use crate::runtime::BoxFuture;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// timeout of call, a [`Duration`] from now or a deadline [`Instant`]
+pub trait IntoDeadline {
+    fn into_deadline(self) -> Instant;
+}
+
+impl IntoDeadline for Duration {
+    #[inline]
+    fn into_deadline(self) -> Instant {
+        let now = Instant::now();
+        // a very long timeout never expires
+        now.checked_add(self).unwrap_or_else(|| now + Duration::from_secs(86400 * 365 * 30))
+    }
+}
+
+impl IntoDeadline for Instant {
+    #[inline]
+    fn into_deadline(self) -> Instant {
+        self
+    }
+}
+
+pin_project_lite::pin_project! {
+    /// return [`Error::Timeout`] if the future is not ready before the sleep
+    pub struct Timeout<F> {
+        #[pin]
+        future: F,
+        sleep: BoxFuture<()>,
+    }
+}
+
+impl<F> Timeout<F> {
+    #[inline]
+    pub(crate) fn new(future: F, sleep: BoxFuture<()>) -> Self {
+        Timeout { future, sleep }
+    }
+}
+
+impl<F: Future> Future for Timeout<F> {
+    type Output = Result<F::Output>;
+
+    #[inline]
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        if let Poll::Ready(output) = this.future.poll(cx) {
+            return Poll::Ready(Ok(output));
+        }
+        match this.sleep.as_mut().poll(cx) {
+            Poll::Ready(()) => Poll::Ready(Err(Error::Timeout)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
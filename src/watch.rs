@@ -0,0 +1,146 @@
+use crate::error::Result;
+use crate::runtime::BoxFuture;
+use crate::state::State;
+use event_listener::EventListener;
+use futures_core::Stream;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// Stream of the version of model, it is changed after every finished write call
+/// the slow consumer only get the latest version, the intermediate versions are coalesced
+/// the stream end if the model is closed
+pub struct Subscribe {
+    state: Arc<State>,
+    seen: u64,
+    listener: Option<EventListener>,
+}
+
+impl Subscribe {
+    /// only the later changes are yielded
+    #[inline]
+    pub(crate) fn new(state: Arc<State>) -> Subscribe {
+        Subscribe {
+            seen: state.subscribe(),
+            state,
+            listener: None,
+        }
+    }
+
+    /// the version of last yielded, or the version when subscribe
+    #[inline]
+    pub fn seen(&self) -> u64 {
+        self.seen
+    }
+
+    /// the version of model now
+    #[inline]
+    pub fn version(&self) -> u64 {
+        self.state.version()
+    }
+
+    /// wait the next change, return the latest version
+    /// return None if the model is closed
+    #[inline]
+    pub async fn changed(&mut self) -> Option<u64> {
+        std::future::poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await
+    }
+}
+
+impl Drop for Subscribe {
+    #[inline]
+    fn drop(&mut self) {
+        self.state.unsubscribe()
+    }
+}
+
+impl Stream for Subscribe {
+    type Item = u64;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<u64>> {
+        loop {
+            let version = self.state.version();
+            if version != self.seen {
+                self.seen = version;
+                self.listener = None;
+                return Poll::Ready(Some(version));
+            }
+            if self.state.is_closed() {
+                self.listener = None;
+                return Poll::Ready(None);
+            }
+            match &mut self.listener {
+                // listen before check again, not lose the change between
+                None => self.listener = Some(self.state.listen()),
+                Some(listener) => {
+                    std::task::ready!(Pin::new(listener).poll(cx));
+                    self.listener = None;
+                }
+            }
+        }
+    }
+}
+
+/// read the version and the projection of the state in one call
+type Read<T> = Box<dyn FnMut() -> BoxFuture<Result<(u64, T)>> + Send>;
+
+/// Stream of the version and the projection of the new state, read after every finished write call
+/// the slow consumer only get the latest state, the intermediate states are coalesced
+/// the stream end if the model is closed or poisoned
+pub struct Watch<T> {
+    subscribe: Subscribe,
+    read: Read<T>,
+    reading: Option<BoxFuture<Result<(u64, T)>>>,
+}
+
+impl<T> Watch<T> {
+    /// read return the version and the projection in one call of the model
+    #[inline]
+    pub(crate) fn new(subscribe: Subscribe, read: impl FnMut() -> BoxFuture<Result<(u64, T)>> + Send + 'static) -> Watch<T> {
+        Watch {
+            subscribe,
+            read: Box::new(read),
+            reading: None,
+        }
+    }
+
+    /// the version of model now
+    #[inline]
+    pub fn version(&self) -> u64 {
+        self.subscribe.version()
+    }
+
+    /// wait the next change, return the latest version and projection
+    /// return None if the model is closed or poisoned
+    #[inline]
+    pub async fn changed(&mut self) -> Option<(u64, T)> {
+        std::future::poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await
+    }
+}
+
+impl<T> Stream for Watch<T> {
+    type Item = (u64, T);
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<(u64, T)>> {
+        let this = &mut *self;
+        loop {
+            if let Some(reading) = &mut this.reading {
+                let output = std::task::ready!(reading.as_mut().poll(cx));
+                this.reading = None;
+                return match output {
+                    // the read see the latest state, skip the versions it include
+                    Ok((version, value)) => {
+                        this.subscribe.seen = this.subscribe.seen.max(version);
+                        Poll::Ready(Some((version, value)))
+                    }
+                    Err(_) => Poll::Ready(None),
+                };
+            }
+            match std::task::ready!(Pin::new(&mut this.subscribe).poll_next(cx)) {
+                Some(_) => this.reading = Some((this.read)()),
+                None => return Poll::Ready(None),
+            }
+        }
+    }
+}
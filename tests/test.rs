@@ -1,3 +1,5 @@
+#![allow(clippy::unused_unit, clippy::useless_vec)]
+
 use anyhow::Result;
 use aqueue::{AQueue, Actor};
 use futures_util::try_join;
@@ -201,15 +203,15 @@ async fn test_count() -> Result<()> {
 
     impl IFoo for Actor<Foo> {
         async fn add_one(&self) -> Result<()> {
-            self.inner_call(|inner| async move {
-                inner.get_mut().add_one();
+            self.inner_call(async move |mut inner| {
+                inner.add_one();
                 Ok(())
             })
             .await
         }
 
         async fn get_str(&self) -> Result<String> {
-            self.inner_call(|inner| async move { Ok(inner.get_mut().get_str()) }).await
+            self.inner_call(async move |inner| Ok(inner.get_str())).await
         }
     }
 
@@ -278,15 +280,15 @@ async fn test_actor() -> Result<()> {
 
     impl FooRunner for Actor<Foo> {
         async fn set(&self, x: i32, y: i32) -> i32 {
-            self.inner_call(|inner| async move { inner.get_mut().set(x, y).await }).await
+            self.inner_call(async move |mut inner| inner.set(x, y).await).await
         }
 
         async fn get(&self) -> (i32, i32, i32) {
-            self.inner_call(|inner| async move { inner.get().get() }).await
+            self.inner_call(async move |inner| inner.get()).await
         }
 
         async fn get_len<'a>(&'a self, b: &'a [u8]) -> usize {
-            self.inner_call(|_| async move { b.len() }).await
+            self.inner_call(async move |_| b.len()).await
         }
     }
 
@@ -322,3 +324,811 @@ async fn test_actor() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_reentrant_check() -> Result<()> {
+    let queue = Arc::new(AQueue::new());
+    let a_queue = queue.clone();
+    let res = queue
+        .checked_run(|_| async move { a_queue.checked_run(|_| async move { 1 }, ()).await }, ())
+        .await?;
+    assert_eq!(res, Err(aqueue::Error::Reentrant));
+
+    let a_queue = queue.clone();
+    let res = queue.checked_run(|_| async move { a_queue.checked_sync_run(|_| 1, ()) }, ()).await?;
+    assert_eq!(res, Err(aqueue::Error::Reentrant));
+
+    let actor = Arc::new(Actor::new(0));
+    let a_actor = actor.clone();
+    let res = actor
+        .checked_inner_call(async move |mut inner| {
+            *inner += 1;
+            a_actor.checked_inner_call(async move |mut inner| *inner += 1).await
+        })
+        .await?;
+    assert_eq!(res, Err(aqueue::Error::Reentrant));
+    assert_eq!(actor.inner_call(async move |inner| { *inner }).await, 1);
+
+    // other tasks are still waiting in queue
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let a_actor = actor.clone();
+    let b_actor = actor.clone();
+    actor
+        .inner_call(async move |mut inner| {
+            tokio::spawn(async move { tx.send(a_actor.inner_call(async move |inner| *inner).await) });
+            sleep(Duration::from_millis(10)).await;
+            assert_eq!(
+                b_actor.checked_inner_call(async move |inner| { *inner }).await,
+                Err(aqueue::Error::Reentrant)
+            );
+            *inner += 1;
+        })
+        .await;
+    assert_eq!(rx.await?, 2);
+
+    let a_actor = actor.clone();
+    let join = tokio::spawn(async move { a_actor.inner_call(async move |_| actor.inner_call(async move |inner| *inner).await).await });
+    assert!(join.await.unwrap_err().is_panic());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_reentrant_actor() -> Result<()> {
+    #[derive(Default)]
+    struct Foo {
+        count: u64,
+    }
+
+    trait IFoo {
+        async fn add(&self) -> u64;
+        async fn get(&self) -> u64;
+        async fn add_and_get(&self) -> (u64, u64);
+    }
+
+    impl IFoo for Actor<Foo> {
+        async fn add(&self) -> u64 {
+            self.inner_call(async move |mut inner| {
+                inner.count += 1;
+                sleep(Duration::from_micros(10)).await;
+                inner.count
+            })
+            .await
+        }
+
+        async fn get(&self) -> u64 {
+            self.inner_call(async move |inner| inner.count).await
+        }
+
+        async fn add_and_get(&self) -> (u64, u64) {
+            self.inner_call(async move |_| {
+                let before = self.get().await;
+                self.add().await;
+                (before, self.get().await)
+            })
+            .await
+        }
+    }
+
+    let queue = &AQueue::new_reentrant();
+    let x = queue
+        .run(
+            |_| async move { queue.run(|x| async move { queue.sync_run(|x| x + 1, x + 1) }, 1).await },
+            (),
+        )
+        .await;
+    assert_eq!(x, 3);
+
+    let foo = Arc::new(unsafe { Actor::new_reentrant(Foo::default()) });
+    let mut joins = vec![];
+    for _ in 0..100 {
+        let foo = foo.clone();
+        joins.push(tokio::spawn(async move {
+            for _ in 0..10 {
+                let (before, after) = foo.add_and_get().await;
+                assert_eq!(before + 1, after);
+            }
+        }));
+    }
+    for join in joins {
+        join.await?;
+    }
+    assert_eq!(foo.get().await, 1000);
+
+    // the outer ref is used again after the nested call
+    let actor = &unsafe { Actor::new_reentrant(0) };
+    let x = actor
+        .inner_call(async move |mut inner| {
+            *inner += 1;
+            actor.inner_call(async move |mut inner| *inner += 1).await;
+            *inner += 1;
+            *inner
+        })
+        .await;
+    assert_eq!(x, 3);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_poison() -> Result<()> {
+    let actor = Arc::new(Actor::new(vec![1, 2, 3]));
+    let a_actor = actor.clone();
+    let join = tokio::spawn(async move {
+        a_actor
+            .inner_call(async move |mut inner| {
+                inner.push(4);
+                sleep(Duration::from_millis(1)).await;
+                panic!("boom");
+            })
+            .await
+    });
+    let err = join.await.unwrap_err().into_panic();
+    assert_eq!(err.downcast_ref::<&str>(), Some(&"boom"));
+    assert!(actor.is_poisoned());
+    assert_eq!(
+        actor.checked_inner_call(async move |inner| { inner.len() }).await,
+        Err(aqueue::Error::Poisoned)
+    );
+
+    actor.clear_poison();
+    assert!(!actor.is_poisoned());
+    assert_eq!(actor.checked_inner_call(async move |inner| { inner.len() }).await, Ok(4));
+
+    let queue = AQueue::new();
+    let res = std::panic::catch_unwind(|| queue.sync_run(|_| panic!("sync boom"), ()));
+    assert!(res.is_err());
+    assert!(queue.is_poisoned());
+    assert_eq!(queue.checked_sync_run(|x| x, 1), Err(aqueue::Error::Poisoned));
+    assert_eq!(queue.checked_run(|x| async move { x }, 1).await, Err(aqueue::Error::Poisoned));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_close() -> Result<()> {
+    let actor = Arc::new(Actor::new(0));
+    let a_actor = actor.clone();
+    let running = tokio::spawn(async move {
+        a_actor
+            .inner_call(async move |mut inner| {
+                sleep(Duration::from_millis(50)).await;
+                *inner += 2;
+            })
+            .await
+    });
+    sleep(Duration::from_millis(10)).await;
+    let a_actor = actor.clone();
+    let waiting = tokio::spawn(async move { a_actor.checked_inner_call(async move |inner| *inner).await });
+    sleep(Duration::from_millis(10)).await;
+    actor.close();
+    assert!(actor.is_closed());
+    running.await?;
+    assert_eq!(waiting.await?, Err(aqueue::Error::Closed));
+
+    let err = actor.checked_inner_call(async move |inner| *inner).await.unwrap_err();
+    assert_eq!(err.to_string(), "queue is closed");
+    let err: anyhow::Error = err.into();
+    assert_eq!(err.downcast_ref::<aqueue::Error>(), Some(&aqueue::Error::Closed));
+    assert_eq!(unsafe { *actor.deref_inner() }, 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_timeout() -> Result<()> {
+    let actor = Arc::new(Actor::new(0));
+    assert_eq!(
+        actor
+            .inner_call_timeout(Duration::from_millis(10), async move |inner| {
+                sleep(Duration::from_millis(100)).await;
+                *inner
+            })
+            .await,
+        Err(aqueue::Error::Timeout)
+    );
+    assert_eq!(
+        actor
+            .inner_call_timeout(Instant::now() + Duration::from_millis(100), async move |mut inner| {
+                *inner += 1;
+                *inner
+            })
+            .await,
+        Ok(1)
+    );
+
+    // the waiting time is counted
+    let a_actor = actor.clone();
+    let running = tokio::spawn(async move { a_actor.inner_call(async move |_| sleep(Duration::from_millis(100)).await).await });
+    sleep(Duration::from_millis(10)).await;
+    assert_eq!(
+        actor.inner_call_timeout(Duration::from_millis(10), async move |inner| { *inner }).await,
+        Err(aqueue::Error::Timeout)
+    );
+    running.await?;
+    assert!(!actor.is_poisoned());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_acquire_timeout() -> Result<()> {
+    let actor = Arc::new(Actor::new(0));
+    let a_actor = actor.clone();
+    let running = tokio::spawn(async move {
+        a_actor
+            .inner_call(async move |mut inner| {
+                sleep(Duration::from_millis(100)).await;
+                *inner += 1;
+            })
+            .await
+    });
+    sleep(Duration::from_millis(10)).await;
+    assert_eq!(
+        actor
+            .inner_call_acquire_timeout(Duration::from_millis(10), async move |inner| { *inner })
+            .await,
+        Err(aqueue::Error::Timeout)
+    );
+    running.await?;
+
+    // once acquired the call is not limited
+    assert_eq!(
+        actor
+            .inner_call_acquire_timeout(Duration::from_millis(10), async move |mut inner| {
+                sleep(Duration::from_millis(50)).await;
+                *inner += 1;
+                *inner
+            })
+            .await,
+        Ok(2)
+    );
+
+    let queue = AQueue::new();
+    assert_eq!(
+        queue
+            .run_acquire_timeout(Instant::now() + Duration::from_millis(10), |x| async move { x + 1 }, 1)
+            .await,
+        Ok(2)
+    );
+    Ok(())
+}
+
+#[cfg(not(any(feature = "async_std_runtime", feature = "smol_runtime")))]
+#[test]
+fn test_no_runtime() {
+    // only tokio is enabled, but the caller is not in a tokio runtime
+    let queue = AQueue::new();
+    assert!(queue.runtime().is_none());
+    assert_eq!(
+        smol::block_on(queue.run_acquire_timeout(Duration::from_millis(10), |x| async move { x }, 1)),
+        Err(aqueue::Error::NoRuntime)
+    );
+}
+
+#[tokio::test]
+async fn test_cancel() -> Result<()> {
+    use aqueue::CancelPolicy;
+    use tokio_util::sync::CancellationToken;
+
+    let actor = Arc::new(Actor::new(0));
+    let a_actor = actor.clone();
+    let running = tokio::spawn(async move {
+        a_actor
+            .inner_call(async move |mut inner| {
+                sleep(Duration::from_millis(100)).await;
+                *inner += 1;
+            })
+            .await
+    });
+    sleep(Duration::from_millis(10)).await;
+
+    // leave the waiting queue
+    let token = CancellationToken::new();
+    let a_actor = actor.clone();
+    let a_token = token.clone();
+    let waiting = tokio::spawn(async move {
+        a_actor
+            .inner_call_with_cancel(a_token.cancelled(), CancelPolicy::OnlyWaiting, async move |inner| *inner)
+            .await
+    });
+    sleep(Duration::from_millis(10)).await;
+    let now = Instant::now();
+    token.cancel();
+    assert_eq!(waiting.await?, Err(aqueue::Error::Cancelled));
+    assert!(now.elapsed() < Duration::from_millis(50));
+    running.await?;
+
+    // only waiting, the running call is finished
+    let token = CancellationToken::new();
+    let a_token = token.clone();
+    let res = actor
+        .inner_call_with_cancel(token.cancelled(), CancelPolicy::OnlyWaiting, async move |mut inner| {
+            a_token.cancel();
+            sleep(Duration::from_millis(10)).await;
+            *inner += 1;
+            *inner
+        })
+        .await;
+    assert_eq!(res, Ok(2));
+
+    // also running, the running call is aborted
+    let token = CancellationToken::new();
+    let a_token = token.clone();
+    let res = actor
+        .inner_call_with_cancel(token.cancelled(), CancelPolicy::AlsoRunning, async move |mut inner| {
+            a_token.cancel();
+            sleep(Duration::from_millis(10)).await;
+            *inner += 1;
+            *inner
+        })
+        .await;
+    assert_eq!(res, Err(aqueue::Error::Cancelled));
+    assert!(!actor.is_poisoned());
+    assert_eq!(actor.inner_call(async move |inner| { *inner }).await, 2);
+
+    let queue = AQueue::new();
+    assert_eq!(
+        queue
+            .run_with_cancel(std::future::pending::<()>(), CancelPolicy::AlsoRunning, |x| async move { x }, 1)
+            .await,
+        Ok(1)
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_detached() -> Result<()> {
+    let actor = Arc::new(Actor::new(vec![]));
+    // the caller is dropped after the call started
+    let res = tokio::time::timeout(
+        Duration::from_millis(10),
+        actor.inner_call_detached(|mut inner| {
+            Box::pin(async move {
+                inner.push(1);
+                sleep(Duration::from_millis(50)).await;
+                inner.push(2);
+            })
+        }),
+    )
+    .await;
+    assert!(res.is_err());
+    assert_eq!(actor.inner_call(async move |inner| { inner.clone() }).await, vec![1, 2]);
+    assert_eq!(
+        actor
+            .inner_call_detached(|mut inner| {
+                Box::pin(async move {
+                    inner.push(3);
+                    inner.len()
+                })
+            })
+            .await,
+        Ok(3)
+    );
+
+    // the panic poison the actor and is resumed on the caller
+    let a_actor = actor.clone();
+    let join = tokio::spawn(async move { a_actor.inner_call_detached(|_| Box::pin(async move { panic!("detached boom") })).await });
+    assert_eq!(*join.await.unwrap_err().into_panic().downcast::<&str>().unwrap(), "detached boom");
+    assert!(actor.is_poisoned());
+
+    // the call dropped by the runtime without run is cancelled, not poison the queue
+    struct Shutdown;
+    impl aqueue::Runtime for Shutdown {
+        fn sleep_until(&self, _deadline: Instant) -> aqueue::BoxFuture<()> {
+            Box::pin(std::future::pending())
+        }
+
+        fn spawn(&self, _future: aqueue::BoxFuture<()>) {}
+    }
+    let queue = AQueue::with_runtime(Shutdown);
+    assert_eq!(queue.run_detached(|x| async move { x }, 1).await, Err(aqueue::Error::Cancelled));
+    assert!(!queue.is_poisoned());
+    assert_eq!(queue.run(|x| async move { x }, 1).await, 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_bounded() -> Result<()> {
+    use aqueue::FullPolicy;
+
+    let actor = Arc::new(Actor::bounded(0, 1, FullPolicy::Reject));
+    let mut joins = vec![];
+    for _ in 0..2 {
+        let a_actor = actor.clone();
+        joins.push(tokio::spawn(async move {
+            a_actor
+                .checked_inner_call(async move |mut inner| {
+                    sleep(Duration::from_millis(50)).await;
+                    *inner += 1;
+                })
+                .await
+        }));
+        sleep(Duration::from_millis(10)).await;
+    }
+    // one running, one pending
+    assert_eq!(actor.checked_inner_call(async move |inner| { *inner }).await, Err(aqueue::Error::Full));
+    for join in joins {
+        assert_eq!(join.await?, Ok(()));
+    }
+    assert_eq!(actor.checked_inner_call(async move |inner| { *inner }).await, Ok(2));
+
+    let actor = Arc::new(Actor::bounded(0, 1, FullPolicy::Wait));
+    let mut joins = vec![];
+    for _ in 0..3 {
+        let a_actor = actor.clone();
+        joins.push(tokio::spawn(async move {
+            a_actor
+                .checked_inner_call(async move |mut inner| {
+                    sleep(Duration::from_millis(20)).await;
+                    *inner += 1;
+                })
+                .await
+        }));
+    }
+    for join in joins {
+        assert_eq!(join.await?, Ok(()));
+    }
+    assert_eq!(actor.inner_call(async move |inner| { *inner }).await, 3);
+
+    // no pending caller, the call only run if the actor is free
+    let actor = Arc::new(Actor::bounded(0, 0, FullPolicy::Reject));
+    assert_eq!(actor.checked_inner_call(async move |mut inner| *inner += 1).await, Ok(()));
+    assert_eq!(actor.checked_sync_inner_call(|mut inner| *inner += 1), Ok(()));
+    let a_actor = actor.clone();
+    let running = tokio::spawn(async move {
+        a_actor
+            .checked_inner_call(async move |mut inner| {
+                sleep(Duration::from_millis(50)).await;
+                *inner += 1;
+            })
+            .await
+    });
+    sleep(Duration::from_millis(10)).await;
+    assert_eq!(actor.checked_inner_call(async move |inner| { *inner }).await, Err(aqueue::Error::Full));
+    assert_eq!(running.await?, Ok(()));
+    assert_eq!(actor.checked_inner_call(async move |inner| { *inner }).await, Ok(3));
+
+    // the contended callers would wait forever
+    assert!(std::panic::catch_unwind(|| AQueue::bounded(0, FullPolicy::Wait)).is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_try_call() -> Result<()> {
+    let actor = Arc::new(Actor::new(0));
+    assert_eq!(
+        actor
+            .try_inner_call(async move |mut inner| {
+                *inner += 1;
+                *inner
+            })
+            .await,
+        Ok(1)
+    );
+    let a_actor = actor.clone();
+    let running = tokio::spawn(async move { a_actor.inner_call(async move |_| sleep(Duration::from_millis(50)).await).await });
+    sleep(Duration::from_millis(10)).await;
+    let now = Instant::now();
+    assert_eq!(actor.try_inner_call(async move |inner| { *inner }).await, Err(aqueue::Error::WouldBlock));
+    assert!(now.elapsed() < Duration::from_millis(10));
+    running.await?;
+
+    let queue = &AQueue::new();
+    assert_eq!(
+        queue.try_run(|x| async move { queue.try_run(|y| async move { y }, x).await }, 1).await,
+        Ok(Err(aqueue::Error::Reentrant))
+    );
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_sync_park() -> Result<()> {
+    let queue = Arc::new(AQueue::new());
+    let a_queue = queue.clone();
+    let running = tokio::spawn(async move { a_queue.run(|_| async move { sleep(Duration::from_millis(50)).await }, ()).await });
+    sleep(Duration::from_millis(10)).await;
+    let a_queue = queue.clone();
+    let now = Instant::now();
+    let sync_call = std::thread::spawn(move || a_queue.sync_run(|_| now.elapsed(), ()));
+    running.await?;
+    assert!(sync_call.join().unwrap() >= Duration::from_millis(30));
+
+    // the blocking pool thread of the blocking call park like a plain thread
+    let actor = Arc::new(Actor::new(0));
+    let a_actor = actor.clone();
+    let running = tokio::spawn(async move {
+        a_actor
+            .inner_call(async move |mut inner| {
+                sleep(Duration::from_millis(50)).await;
+                *inner += 1;
+            })
+            .await
+    });
+    sleep(Duration::from_millis(10)).await;
+    let a_queue = queue.clone();
+    let a_actor = actor.clone();
+    let sync_call = tokio::spawn(async move {
+        AQueue::new()
+            .run_blocking(
+                move |_| {
+                    let now = Instant::now();
+                    let res = a_actor.checked_sync_inner_call(|mut inner| {
+                        *inner += 1;
+                        *inner
+                    });
+                    (res, now.elapsed(), a_queue.checked_sync_run(|x| x, 1))
+                },
+                (),
+            )
+            .await
+    });
+    running.await?;
+    let (res, elapsed, run) = sync_call.await??;
+    assert_eq!(res, Ok(2));
+    assert!(elapsed >= Duration::from_millis(30));
+    assert_eq!(run, Ok(1));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_block_call() -> Result<()> {
+    let actor = Arc::new(Actor::new(Vec::new()));
+    let a_actor = actor.clone();
+    let running = tokio::spawn(async move {
+        a_actor
+            .inner_call(async move |mut inner| {
+                sleep(Duration::from_millis(50)).await;
+                inner.push(1);
+            })
+            .await
+    });
+    sleep(Duration::from_millis(10)).await;
+    let a_actor = actor.clone();
+    let block_call = std::thread::spawn(move || {
+        a_actor.block_inner_call(async move |mut inner| inner.push(2));
+        a_actor.sync_inner_call(|mut inner| inner.push(3));
+        a_actor.sync_inner_call(|inner| inner.clone())
+    });
+    running.await?;
+    assert_eq!(block_call.join().unwrap(), vec![1, 2, 3]);
+
+    assert_eq!(actor.checked_sync_inner_call(|inner| inner.len()), Ok(3));
+    assert!(matches!(
+        actor.checked_block_inner_call(async move |inner| { inner.len() }),
+        Err(aqueue::Error::AsyncWorker)
+    ));
+    // inside a queue call polled by the async worker
+    let queue = AQueue::new();
+    let res = queue
+        .run(|_| async { actor.checked_block_inner_call(async move |inner| inner.len()) }, ())
+        .await;
+    assert!(matches!(res, Err(aqueue::Error::AsyncWorker)));
+
+    // the blocking pool thread of the blocking call is in the runtime context, but it is not an async worker
+    let a_actor = actor.clone();
+    let res = queue
+        .run_blocking(
+            move |_| {
+                a_actor.block_inner_call(async move |mut inner| inner.push(4));
+                a_actor.checked_block_inner_call(async move |inner| inner.len())
+            },
+            (),
+        )
+        .await?;
+    assert_eq!(res, Ok(4));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sync_on_worker() -> Result<()> {
+    let queue = Arc::new(AQueue::new());
+    let a_queue = queue.clone();
+    let running = tokio::spawn(async move { a_queue.run(|_| async move { sleep(Duration::from_millis(50)).await }, ()).await });
+    sleep(Duration::from_millis(10)).await;
+    // park the only worker thread would never release the queue
+    assert_eq!(queue.checked_sync_run(|x| x, 1), Err(aqueue::Error::AsyncWorker));
+    let a_queue = queue.clone();
+    assert!(std::panic::catch_unwind(move || a_queue.sync_run(|x| x, 1)).is_err());
+    running.await?;
+    assert!(!queue.is_poisoned());
+    assert_eq!(queue.checked_sync_run(|x| x, 1), Ok(1));
+
+    let actor = Arc::new(Actor::new(0));
+    let a_actor = actor.clone();
+    let running = tokio::spawn(async move { a_actor.inner_call(async move |_| sleep(Duration::from_millis(50)).await).await });
+    sleep(Duration::from_millis(10)).await;
+    let a_actor = actor.clone();
+    let res = tokio::spawn(async move { a_actor.checked_sync_inner_call(|inner| *inner) }).await?;
+    assert_eq!(res, Err(aqueue::Error::AsyncWorker));
+    running.await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_call_blocking() -> Result<()> {
+    let actor = Arc::new(Actor::new((0..10000).rev().collect::<Vec<u32>>()));
+    let a_actor = actor.clone();
+    let sorting = tokio::spawn(async move {
+        a_actor
+            .inner_call_blocking(|mut inner| {
+                std::thread::sleep(Duration::from_millis(50));
+                inner.sort();
+                inner[0]
+            })
+            .await
+    });
+    // the current thread worker is not stalled by the blocking call
+    let now = Instant::now();
+    sleep(Duration::from_millis(10)).await;
+    assert!(now.elapsed() < Duration::from_millis(40));
+    // the later call wait for the blocking call
+    assert_eq!(actor.inner_call(async move |inner| { inner[1] }).await, 1);
+    assert_eq!(sorting.await?, Ok(0));
+
+    // the blocking call run to completion even if the caller is dropped
+    let a_actor = actor.clone();
+    let caller = tokio::spawn(async move {
+        a_actor
+            .inner_call_blocking(|mut inner| {
+                std::thread::sleep(Duration::from_millis(50));
+                inner.clear();
+            })
+            .await
+    });
+    sleep(Duration::from_millis(10)).await;
+    caller.abort();
+    assert_eq!(actor.inner_call(async move |inner| { inner.len() }).await, 0);
+
+    let a_actor = actor.clone();
+    let join = tokio::spawn(async move { a_actor.inner_call_blocking(|_| panic!("blocking boom")).await });
+    assert_eq!(*join.await.unwrap_err().into_panic().downcast::<&str>().unwrap(), "blocking boom");
+    assert!(actor.is_poisoned());
+
+    let queue = AQueue::new();
+    assert_eq!(
+        queue.checked_run(|_| async { queue.run_blocking(|x| x, 1).await }, ()).await?,
+        Err(aqueue::Error::Reentrant)
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_async_closure() -> Result<()> {
+    let actor = Actor::new(Vec::new());
+    let name = String::from("aqueue");
+    let buff = [1u8, 2, 3];
+    // borrow the caller args without clone
+    let len = actor
+        .inner_call(async |mut inner| {
+            inner.extend_from_slice(&buff);
+            sleep(Duration::from_millis(1)).await;
+            inner.extend_from_slice(name.as_bytes());
+            inner.len()
+        })
+        .await;
+    assert_eq!(len, 9);
+    assert_eq!(name, "aqueue");
+
+    let a_actor = &actor;
+    let res = actor
+        .checked_inner_call(async |_| a_actor.checked_inner_call(async |inner| inner.len()).await)
+        .await?;
+    assert_eq!(res, Err(aqueue::Error::Reentrant));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_unsized() -> Result<()> {
+    trait Counter: Send {
+        fn incr(&mut self) -> u64;
+    }
+
+    struct One(u64);
+
+    impl Counter for One {
+        fn incr(&mut self) -> u64 {
+            self.0 += 1;
+            self.0
+        }
+    }
+
+    struct Ten(u64);
+
+    impl Counter for Ten {
+        fn incr(&mut self) -> u64 {
+            self.0 += 10;
+            self.0
+        }
+    }
+
+    let actors: Vec<Arc<Actor<dyn Counter>>> = vec![Arc::new(Actor::new(One(0))), Arc::new(Actor::new(Ten(0)))];
+    for actor in &actors {
+        actor.inner_call(async move |mut inner| inner.incr()).await;
+        actor.sync_inner_call(|mut inner| inner.incr());
+    }
+    assert_eq!(actors[0].inner_call(async |mut inner| inner.incr()).await, 3);
+    assert_eq!(actors[1].inner_call_blocking(|mut inner| inner.incr()).await, Ok(30));
+
+    let pc: Box<aqueue::PCModel<[u32]>> = Box::new(aqueue::PCModel::new([1, 2, 3], 2));
+    assert_eq!(pc.call(async move |inner| { inner.len() }).await, 3);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_lock_guard() -> Result<()> {
+    let queue = Arc::new(AQueue::new());
+    let count = Arc::new(std::sync::atomic::AtomicU32::new(0));
+
+    // the guard hold the queue over multiple awaits
+    {
+        let _guard = queue.lock().await;
+        count.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        sleep(Duration::from_millis(10)).await;
+        count.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        assert!(queue.try_run(|_| async move {}, ()).await.is_err());
+    }
+    assert_eq!(queue.try_run(|_| async move {}, ()).await, Ok(()));
+
+    // move the owned guard into the spawned task
+    let guard = queue.lock_arc().await;
+    let a_count = count.clone();
+    let join = tokio::spawn(async move {
+        let _guard = guard;
+        sleep(Duration::from_millis(20)).await;
+        a_count.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+    });
+    let b_count = count.clone();
+    let value = queue.run(|_| async move { b_count.load(std::sync::atomic::Ordering::SeqCst) }, ()).await;
+    assert_eq!(value, 3);
+    join.await?;
+
+    // lock inside the call of the queue
+    let res = queue.run(|queue| async move { queue.checked_lock().await.err() }, &*queue).await;
+    assert_eq!(res, Some(aqueue::Error::Reentrant));
+
+    // the panic while holding the guard poison the queue
+    let a_queue = queue.clone();
+    let join = tokio::spawn(async move {
+        let _guard = a_queue.lock_arc().await;
+        panic!("boom")
+    });
+    assert!(join.await.unwrap_err().is_panic());
+    assert!(queue.is_poisoned());
+    assert_eq!(queue.checked_lock().await.err(), Some(aqueue::Error::Poisoned));
+    queue.clear_poison();
+    queue.close();
+    assert_eq!(queue.checked_lock_arc().await.err(), Some(aqueue::Error::Closed));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_subscribe() -> Result<()> {
+    use futures_util::StreamExt;
+
+    let actor = Arc::new(Actor::new(0));
+    let mut watch = actor.watch(|inner| *inner * 10);
+    let mut versions = actor.subscribe();
+
+    let a_actor = actor.clone();
+    let listener = tokio::spawn(async move {
+        let mut seen = Vec::new();
+        while let Some((version, value)) = watch.next().await {
+            seen.push((version, value));
+            if value == 30 {
+                break;
+            }
+        }
+        seen
+    });
+    sleep(Duration::from_millis(10)).await;
+    for _ in 0..3 {
+        a_actor.inner_call(async move |mut inner| *inner += 1).await;
+        sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(listener.await?, [(1, 10), (2, 20), (3, 30)]);
+
+    // coalesce the intermediate versions
+    actor.sync_inner_call(|mut inner| *inner += 1);
+    actor.inner_call(async |mut inner| *inner += 1).await;
+    assert_eq!(versions.next().await, Some(5));
+    assert_eq!(actor.version(), 5);
+    actor.close();
+    assert_eq!(versions.changed().await, None);
+    Ok(())
+}
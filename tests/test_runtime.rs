@@ -0,0 +1,124 @@
+use aqueue::{Actor, AsyncStdRuntime, BoxFuture, PCModel, Runtime, RwModel, SmolRuntime, TokioRuntime};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// count the sleep of tokio runtime
+#[derive(Default)]
+struct CountRuntime {
+    sleeps: AtomicUsize,
+}
+
+impl Runtime for CountRuntime {
+    fn sleep_until(&self, deadline: Instant) -> BoxFuture<()> {
+        self.sleeps.fetch_add(1, Ordering::SeqCst);
+        TokioRuntime.sleep_until(deadline)
+    }
+
+    fn spawn(&self, future: BoxFuture<()>) {
+        TokioRuntime.spawn(future)
+    }
+}
+
+#[tokio::test]
+async fn test_custom_runtime() {
+    let runtime = Arc::new(CountRuntime::default());
+    let actor = Actor::with_runtime(0, runtime.clone());
+    assert_eq!(
+        actor
+            .inner_call_timeout(Duration::from_millis(10), async move |inner| {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                *inner
+            })
+            .await,
+        Err(aqueue::Error::Timeout)
+    );
+    assert_eq!(
+        actor
+            .inner_call_acquire_timeout(Duration::from_millis(10), async move |inner| { *inner })
+            .await,
+        Ok(0)
+    );
+    assert_eq!(runtime.sleeps.load(Ordering::SeqCst), 2);
+
+    // the default spawn_blocking run on a new thread
+    let actor = Arc::new(actor);
+    let thread = std::thread::current().id();
+    assert_eq!(actor.inner_call_blocking(move |_| std::thread::current().id() != thread).await, Ok(true));
+
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let runtime: &dyn Runtime = &*runtime;
+    runtime.spawn(Box::pin(async move {
+        tx.send(1).unwrap();
+    }));
+    assert_eq!(rx.await, Ok(1));
+    assert_eq!(
+        runtime.timeout(Duration::from_millis(10), std::future::pending::<()>()).await,
+        Err(aqueue::Error::Timeout)
+    );
+}
+
+#[test]
+fn test_async_std_runtime() {
+    async_std::task::block_on(async {
+        let model = Arc::new(RwModel::with_runtime(0, AsyncStdRuntime));
+        assert_eq!(
+            model
+                .call_mut_timeout(Duration::from_millis(10), async move |mut inner| {
+                    async_std::task::sleep(Duration::from_millis(100)).await;
+                    *inner += 1;
+                })
+                .await,
+            Err(aqueue::Error::Timeout)
+        );
+        assert_eq!(model.call_timeout(Duration::from_millis(100), async move |inner| { *inner }).await, Ok(0));
+        assert_eq!(model.call_mut_blocking(|mut inner| *inner += 1).await, Ok(()));
+        assert_eq!(model.call_blocking(|inner| *inner).await, Ok(1));
+    })
+}
+
+#[test]
+fn test_smol_runtime() {
+    smol::block_on(async {
+        let model = Arc::new(PCModel::with_runtime(1, 1, SmolRuntime));
+        assert_eq!(
+            model
+                .call_timeout(Duration::from_millis(10), async move |inner| {
+                    smol::Timer::after(Duration::from_millis(100)).await;
+                    *inner
+                })
+                .await,
+            Err(aqueue::Error::Timeout)
+        );
+        assert_eq!(
+            model
+                .call_acquire_timeout(Duration::from_millis(100), async move |inner| { *inner })
+                .await,
+            Ok(1)
+        );
+        assert_eq!(model.call_blocking(|inner| *inner + 1).await, Ok(2));
+    })
+}
+
+#[tokio::test]
+async fn test_default_runtime() {
+    // tokio in a tokio runtime if several runtimes are enabled
+    assert!(aqueue::default_runtime().is_some());
+    let queue = aqueue::AQueue::new();
+    assert!(queue.runtime().is_some());
+    assert_eq!(queue.run_acquire_timeout(Duration::from_millis(10), |x| async move { x }, 1).await, Ok(1));
+}
+
+#[test]
+fn test_default_runtime_not_tokio() {
+    // not in a tokio runtime, the default runtime is not tokio even if it is enabled
+    let queue = aqueue::AQueue::new();
+    async_std::task::block_on(async {
+        assert_eq!(queue.run_acquire_timeout(Duration::from_millis(10), |x| async move { x }, 1).await, Ok(1));
+        let actor = Arc::new(Actor::new(0));
+        assert_eq!(actor.inner_call_detached(|mut inner| Box::pin(async move { *inner += 1 })).await, Ok(()));
+    });
+    smol::block_on(async {
+        assert_eq!(queue.run_acquire_timeout(Duration::from_millis(10), |x| async move { x }, 2).await, Ok(2));
+    });
+}
@@ -1,3 +1,5 @@
+#![allow(clippy::unused_unit, clippy::useless_vec)]
+
 use anyhow::Result;
 use aqueue::{RwModel, RwQueue};
 
@@ -188,7 +190,7 @@ async fn test_count() -> Result<()> {
 
     impl IFoo for RwModel<Foo> {
         async fn add_one(&self) -> Result<()> {
-            self.call_mut(|mut inner| async move {
+            self.call_mut(async move |mut inner| {
                 inner.add_one();
                 Ok(())
             })
@@ -196,7 +198,7 @@ async fn test_count() -> Result<()> {
         }
 
         async fn get_str(&self) -> Result<String> {
-            self.call(|inner| async move { Ok(inner.get_str()) }).await
+            self.call(async move |inner| Ok(inner.get_str())).await
         }
     }
 
@@ -281,15 +283,15 @@ async fn test_actor() -> Result<()> {
 
     impl FooRunner for RwModel<Foo> {
         async fn set(&self, x: i32, y: i32) -> i32 {
-            self.call_mut(|mut inner| async move { inner.set(x, y).await }).await
+            self.call_mut(async move |mut inner| inner.set(x, y).await).await
         }
 
         async fn get(&self) -> (i32, i32, i32) {
-            self.call(|inner| async move { inner.get() }).await
+            self.call(async move |inner| inner.get()).await
         }
 
         async fn get_len<'a>(&'a self, b: &'a [u8]) -> usize {
-            self.call(|_| async move { b.len() }).await
+            self.call(async move |_| b.len()).await
         }
     }
 
@@ -352,11 +354,11 @@ async fn test_rw_model() -> Result<()> {
 
     impl IFoo for RwModel<Foo> {
         async fn get(&self) -> i32 {
-            self.call(|inner| async move { inner.get().await }).await
+            self.call(async move |inner| inner.get().await).await
         }
 
         async fn set(&self, i: i32) {
-            self.call_mut(|mut inner| async move { inner.set(i).await }).await
+            self.call_mut(async move |mut inner| inner.set(i).await).await
         }
     }
 
@@ -381,3 +383,830 @@ async fn test_rw_model() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_reentrant_check() -> Result<()> {
+    let model = Arc::new(RwModel::new(0));
+    let a_model = model.clone();
+    let res = model
+        .checked_call(async move |_| a_model.checked_call(async move |inner| *inner).await)
+        .await?;
+    assert_eq!(res, Err(aqueue::Error::Reentrant));
+
+    let a_model = model.clone();
+    let res = model
+        .checked_call_mut(async move |_| a_model.checked_call_mut(async move |mut inner| *inner += 1).await)
+        .await?;
+    assert_eq!(res, Err(aqueue::Error::Reentrant));
+
+    let a_model = model.clone();
+    let res = model
+        .checked_call(async move |_| a_model.checked_call_mut(async move |_| {}).await)
+        .await?;
+    assert_eq!(res, Err(aqueue::Error::Reentrant));
+
+    let a_model = model.clone();
+    let res = model
+        .checked_call_mut(async move |_| std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| a_model.sync_call(|inner| *inner))))
+        .await?;
+    assert!(res.is_err());
+
+    let a_model = model.clone();
+    let join = tokio::spawn(async move { a_model.call_mut(async move |_| model.call(async move |inner| *inner).await).await });
+    assert!(join.await.unwrap_err().is_panic());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_poison() -> Result<()> {
+    let model = Arc::new(RwModel::new(0));
+    let a_model = model.clone();
+    let join = tokio::spawn(async move { a_model.call(async move |_| panic!("read boom")).await });
+    assert!(join.await.unwrap_err().is_panic());
+    assert!(!model.is_poisoned());
+
+    let a_model = model.clone();
+    let join = tokio::spawn(async move {
+        a_model
+            .call_mut(async move |mut inner| {
+                *inner += 1;
+                panic!("write boom")
+            })
+            .await
+    });
+    let err = join.await.unwrap_err().into_panic();
+    assert_eq!(err.downcast_ref::<&str>(), Some(&"write boom"));
+    assert!(model.is_poisoned());
+    assert_eq!(model.checked_call(async move |inner| { *inner }).await, Err(aqueue::Error::Poisoned));
+    assert_eq!(model.checked_call_mut(async move |inner| { *inner }).await, Err(aqueue::Error::Poisoned));
+
+    model.clear_poison();
+    assert_eq!(model.checked_call(async move |inner| { *inner }).await, Ok(1));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_close() -> Result<()> {
+    let model = RwModel::new(0);
+    assert_eq!(model.checked_call_mut(async move |mut inner| { *inner += 1 }).await, Ok(()));
+    model.close();
+    assert!(model.is_closed());
+    assert_eq!(model.checked_call(async move |inner| { *inner }).await, Err(aqueue::Error::Closed));
+    assert_eq!(
+        model.checked_call_mut(async move |mut inner| { *inner += 1 }).await,
+        Err(aqueue::Error::Closed)
+    );
+    let queue = RwQueue::new();
+    queue.close();
+    assert_eq!(queue.checked_sync_read_run(|x| x, 1), Err(aqueue::Error::Closed));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_timeout() -> Result<()> {
+    let model = RwModel::new(0);
+    assert_eq!(
+        model
+            .call_mut_timeout(Duration::from_millis(10), async move |mut inner| {
+                sleep(Duration::from_millis(100)).await;
+                *inner += 1;
+            })
+            .await,
+        Err(aqueue::Error::Timeout)
+    );
+    assert_eq!(
+        model
+            .call_timeout(Duration::from_millis(10), async move |inner| {
+                sleep(Duration::from_millis(100)).await;
+                *inner
+            })
+            .await,
+        Err(aqueue::Error::Timeout)
+    );
+    assert_eq!(
+        model
+            .call_mut_timeout(Instant::now() + Duration::from_millis(100), async move |mut inner| {
+                *inner += 1;
+            })
+            .await,
+        Ok(())
+    );
+    assert_eq!(model.call_timeout(Duration::from_millis(100), async move |inner| { *inner }).await, Ok(1));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_acquire_timeout() -> Result<()> {
+    let model = Arc::new(RwModel::new(0));
+    let a_model = model.clone();
+    let running = tokio::spawn(async move {
+        a_model
+            .call_mut(async move |mut inner| {
+                sleep(Duration::from_millis(100)).await;
+                *inner += 1;
+            })
+            .await
+    });
+    sleep(Duration::from_millis(10)).await;
+    assert_eq!(
+        model.call_acquire_timeout(Duration::from_millis(10), async move |inner| { *inner }).await,
+        Err(aqueue::Error::Timeout)
+    );
+    assert_eq!(
+        model
+            .call_mut_acquire_timeout(Duration::from_millis(10), async move |mut inner| { *inner += 1 })
+            .await,
+        Err(aqueue::Error::Timeout)
+    );
+    running.await?;
+
+    assert_eq!(
+        model
+            .call_mut_acquire_timeout(Duration::from_millis(10), async move |mut inner| {
+                sleep(Duration::from_millis(50)).await;
+                *inner += 1;
+            })
+            .await,
+        Ok(())
+    );
+    assert_eq!(
+        model
+            .call_acquire_timeout(Duration::from_millis(10), async move |inner| {
+                sleep(Duration::from_millis(50)).await;
+                *inner
+            })
+            .await,
+        Ok(2)
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cancel() -> Result<()> {
+    use aqueue::CancelPolicy;
+
+    let model = Arc::new(RwModel::new(0));
+    let a_model = model.clone();
+    let running = tokio::spawn(async move {
+        a_model
+            .call_mut(async move |mut inner| {
+                sleep(Duration::from_millis(100)).await;
+                *inner += 1;
+            })
+            .await
+    });
+    sleep(Duration::from_millis(10)).await;
+    assert_eq!(
+        model
+            .call_with_cancel(sleep(Duration::from_millis(10)), CancelPolicy::OnlyWaiting, async move |inner| { *inner })
+            .await,
+        Err(aqueue::Error::Cancelled)
+    );
+    assert_eq!(
+        model
+            .call_mut_with_cancel(sleep(Duration::from_millis(10)), CancelPolicy::OnlyWaiting, async move |mut inner| {
+                *inner += 1
+            })
+            .await,
+        Err(aqueue::Error::Cancelled)
+    );
+    running.await?;
+
+    assert_eq!(
+        model
+            .call_mut_with_cancel(sleep(Duration::from_millis(10)), CancelPolicy::AlsoRunning, async move |mut inner| {
+                sleep(Duration::from_millis(50)).await;
+                *inner += 1
+            })
+            .await,
+        Err(aqueue::Error::Cancelled)
+    );
+    assert_eq!(
+        model
+            .call_with_cancel(sleep(Duration::from_millis(10)), CancelPolicy::OnlyWaiting, async move |inner| {
+                sleep(Duration::from_millis(50)).await;
+                *inner
+            })
+            .await,
+        Ok(1)
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_detached() -> Result<()> {
+    let model = Arc::new(RwModel::new((0, 0)));
+    let res = tokio::time::timeout(
+        Duration::from_millis(10),
+        model.call_mut_detached(|mut inner| {
+            Box::pin(async move {
+                inner.0 += 1;
+                sleep(Duration::from_millis(50)).await;
+                inner.1 += 1;
+            })
+        }),
+    )
+    .await;
+    assert!(res.is_err());
+    assert_eq!(model.call(async move |inner| { *inner }).await, (1, 1));
+    assert_eq!(model.call_mut_detached(|inner| Box::pin(async move { inner.0 })).await, Ok(1));
+
+    // the queue fn take an owned arg
+    let queue = RwQueue::new();
+    let value = Arc::new(std::sync::atomic::AtomicU32::new(1));
+    let res = queue
+        .write_run_detached(
+            |value| async move { value.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1 },
+            value.clone(),
+        )
+        .await;
+    assert_eq!(res, Ok(2));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_bounded() -> Result<()> {
+    use aqueue::FullPolicy;
+
+    let model = Arc::new(RwModel::bounded(0, 1, FullPolicy::Reject));
+    let mut joins = vec![];
+    for _ in 0..2 {
+        let a_model = model.clone();
+        joins.push(tokio::spawn(async move {
+            a_model
+                .checked_call_mut(async move |mut inner| {
+                    sleep(Duration::from_millis(50)).await;
+                    *inner += 1;
+                })
+                .await
+        }));
+        sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(model.checked_call(async move |inner| { *inner }).await, Err(aqueue::Error::Full));
+    assert_eq!(
+        model.call_acquire_timeout(Duration::from_millis(10), async move |inner| { *inner }).await,
+        Err(aqueue::Error::Full)
+    );
+    for join in joins {
+        assert_eq!(join.await?, Ok(()));
+    }
+    assert_eq!(model.checked_call(async move |inner| { *inner }).await, Ok(2));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_try_call() -> Result<()> {
+    let model = Arc::new(RwModel::new(0));
+    let a_model = model.clone();
+    let reading = tokio::spawn(async move { a_model.call(async move |_| sleep(Duration::from_millis(50)).await).await });
+    sleep(Duration::from_millis(10)).await;
+    assert_eq!(model.try_call(async move |inner| { *inner }).await, Ok(0));
+    assert_eq!(
+        model.try_call_mut(async move |mut inner| { *inner += 1 }).await,
+        Err(aqueue::Error::WouldBlock)
+    );
+    reading.await?;
+
+    let a_model = model.clone();
+    let writing = tokio::spawn(async move {
+        a_model
+            .call_mut(async move |mut inner| {
+                sleep(Duration::from_millis(50)).await;
+                *inner += 1;
+            })
+            .await
+    });
+    sleep(Duration::from_millis(10)).await;
+    assert_eq!(model.try_call(async move |inner| { *inner }).await, Err(aqueue::Error::WouldBlock));
+    writing.await?;
+    assert_eq!(model.try_call_mut(async move |mut inner| { *inner += 1 }).await, Ok(()));
+    assert_eq!(model.try_call(async move |inner| { *inner }).await, Ok(2));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_sync_park() -> Result<()> {
+    let model = Arc::new(RwModel::new(0));
+    let a_model = model.clone();
+    let writing = tokio::spawn(async move {
+        a_model
+            .call_mut(async move |mut inner| {
+                sleep(Duration::from_millis(100)).await;
+                *inner += 1;
+            })
+            .await
+    });
+    sleep(Duration::from_millis(10)).await;
+
+    // the sync writer park until the async writer release the lock
+    let a_model = model.clone();
+    let sync_writer = std::thread::spawn(move || {
+        a_model.sync_mut_call(|mut inner| {
+            *inner += 1;
+            *inner
+        })
+    });
+    writing.await?;
+    assert_eq!(sync_writer.join().unwrap(), 2);
+
+    // the blocking pool thread of the blocking call park like a plain thread
+    let a_model = model.clone();
+    let writing = tokio::spawn(async move { a_model.call_mut(async move |_| sleep(Duration::from_millis(50)).await).await });
+    sleep(Duration::from_millis(10)).await;
+    let a_model = model.clone();
+    let sync_writer = tokio::spawn(async move {
+        aqueue::AQueue::new()
+            .run_blocking(
+                move |_| {
+                    let res = a_model.checked_sync_mut_call(|mut inner| *inner += 1);
+                    (res, a_model.checked_sync_call(|inner| *inner))
+                },
+                (),
+            )
+            .await
+    });
+    writing.await?;
+    assert_eq!(sync_writer.await??, (Ok(()), Ok(3)));
+
+    // the sync writer is not starved by a stream of readers
+    let stop = Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let mut readers = vec![];
+    for _ in 0..4 {
+        let a_model = model.clone();
+        let stop = stop.clone();
+        readers.push(tokio::spawn(async move {
+            while !stop.load(std::sync::atomic::Ordering::Relaxed) {
+                a_model.call(async move |_| sleep(Duration::from_millis(5)).await).await;
+            }
+        }));
+    }
+    sleep(Duration::from_millis(10)).await;
+    let a_model = model.clone();
+    let now = Instant::now();
+    let sync_writer = std::thread::spawn(move || a_model.sync_mut_call(|mut inner| *inner += 1));
+    sync_writer.join().unwrap();
+    assert!(now.elapsed() < Duration::from_millis(500));
+    stop.store(true, std::sync::atomic::Ordering::Relaxed);
+    for reader in readers {
+        reader.await?;
+    }
+    assert_eq!(model.call(async move |inner| { *inner }).await, 4);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_block_call() -> Result<()> {
+    let model = Arc::new(RwModel::new(0u32));
+    let a_model = model.clone();
+    let running = tokio::spawn(async move {
+        a_model
+            .call_mut(async move |mut inner| {
+                sleep(Duration::from_millis(50)).await;
+                *inner += 1;
+            })
+            .await
+    });
+    sleep(Duration::from_millis(10)).await;
+    let a_model = model.clone();
+    let block_call = std::thread::spawn(move || {
+        a_model.block_call_mut(async move |mut inner| *inner *= 10);
+        a_model.block_call(async move |inner| *inner)
+    });
+    running.await?;
+    assert_eq!(block_call.join().unwrap(), 10);
+
+    assert!(matches!(
+        model.checked_block_call(async move |inner| { *inner }),
+        Err(aqueue::Error::AsyncWorker)
+    ));
+    // inside a queue call polled by the async worker
+    let queue = aqueue::AQueue::new();
+    let res = queue.run(|_| async { model.checked_block_call(async move |inner| *inner) }, ()).await;
+    assert!(matches!(res, Err(aqueue::Error::AsyncWorker)));
+
+    // the blocking pool thread of the blocking call is in the runtime context, but it is not an async worker
+    let a_model = model.clone();
+    let res = queue
+        .run_blocking(
+            move |_| {
+                a_model.block_call_mut(async move |mut inner| *inner += 1);
+                a_model.checked_block_call(async move |inner| *inner)
+            },
+            (),
+        )
+        .await?;
+    assert_eq!(res, Ok(11));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sync_on_worker() -> Result<()> {
+    let model = Arc::new(RwModel::new(0u32));
+    let a_model = model.clone();
+    let writing = tokio::spawn(async move {
+        a_model
+            .call_mut(async move |mut inner| {
+                sleep(Duration::from_millis(50)).await;
+                *inner += 1;
+            })
+            .await
+    });
+    sleep(Duration::from_millis(10)).await;
+    // park the only worker thread would never release the write lock
+    assert_eq!(model.checked_sync_mut_call(|mut inner| *inner += 1), Err(aqueue::Error::AsyncWorker));
+    assert_eq!(model.checked_sync_call(|inner| *inner), Err(aqueue::Error::AsyncWorker));
+    writing.await?;
+    assert_eq!(model.checked_sync_mut_call(|mut inner| *inner += 1), Ok(()));
+    assert_eq!(model.checked_sync_call(|inner| *inner), Ok(2));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_call_blocking() -> Result<()> {
+    let model = Arc::new(RwModel::new((0..10000).rev().collect::<Vec<u32>>()));
+    let a_model = model.clone();
+    let sorting = tokio::spawn(async move {
+        a_model
+            .call_mut_blocking(|mut inner| {
+                std::thread::sleep(Duration::from_millis(50));
+                inner.sort();
+            })
+            .await
+    });
+    sleep(Duration::from_millis(10)).await;
+    // the reader wait for the blocking writer
+    assert_eq!(model.call(async move |inner| { inner[0] }).await, 0);
+    assert_eq!(sorting.await?, Ok(()));
+    assert_eq!(
+        model.call_blocking(|inner| inner.iter().map(|x| *x as u64).sum::<u64>()).await,
+        Ok(49995000)
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_async_closure() -> Result<()> {
+    let model = RwModel::new(Vec::new());
+    let names = vec![String::from("a"), String::from("b")];
+    model
+        .call_mut(async |mut inner| {
+            for name in &names {
+                inner.push(name.as_str().to_owned());
+            }
+        })
+        .await;
+    let prefix = "a";
+    let count = model
+        .call(async |inner| inner.iter().filter(|name| name.starts_with(prefix)).count())
+        .await;
+    assert_eq!(count, 1);
+    assert_eq!(model.checked_call(async |inner| inner.len()).await, Ok(names.len()));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_unsized() -> Result<()> {
+    trait Storage: Send + Sync {
+        fn put(&mut self, value: u32);
+        fn sum(&self) -> u32;
+    }
+
+    #[derive(Default)]
+    struct MemStorage(Vec<u32>);
+
+    impl Storage for MemStorage {
+        fn put(&mut self, value: u32) {
+            self.0.push(value);
+        }
+        fn sum(&self) -> u32 {
+            self.0.iter().sum()
+        }
+    }
+
+    struct CountStorage(u32);
+
+    impl Storage for CountStorage {
+        fn put(&mut self, _value: u32) {
+            self.0 += 1;
+        }
+        fn sum(&self) -> u32 {
+            self.0
+        }
+    }
+
+    // swap the backends behind one type
+    let models: Vec<Arc<RwModel<dyn Storage>>> = vec![Arc::new(RwModel::new(MemStorage::default())), Arc::new(RwModel::new(CountStorage(0)))];
+    for model in &models {
+        model.call_mut(async move |mut inner| inner.put(10)).await;
+        model.call_mut(async |mut inner| inner.put(20)).await;
+        model.sync_mut_call(|mut inner| inner.put(30));
+    }
+    assert_eq!(models[0].call(async move |inner| { inner.sum() }).await, 60);
+    assert_eq!(models[1].call(async |inner| inner.sum()).await, 3);
+    assert_eq!(models[1].call_mut_blocking(|mut inner| inner.put(1)).await, Ok(()));
+    assert_eq!(models[1].sync_call(|inner| inner.sum()), 4);
+
+    let slice: Box<RwModel<[u32]>> = Box::new(RwModel::new([1, 2, 3]));
+    slice.call_mut(async move |mut inner| inner.reverse()).await;
+    assert_eq!(slice.call(async move |inner| { inner.to_vec() }).await, vec![3, 2, 1]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_lock_guard() -> Result<()> {
+    let model = Arc::new(RwModel::new(vec![1, 2, 3]));
+
+    // the guards hold the model over multiple awaits
+    {
+        let mut guard = model.write().await;
+        guard.push(4);
+        sleep(Duration::from_millis(10)).await;
+        guard.push(5);
+        assert!(model.try_call(async move |_| {}).await.is_err());
+    }
+    {
+        let a = model.read().await;
+        let b = model.read().await;
+        sleep(Duration::from_millis(10)).await;
+        assert_eq!(a.len() + b.len(), 10);
+        assert!(model.try_call_mut(async move |_| {}).await.is_err());
+    }
+
+    // move the owned guards into the spawned task
+    let queue = Arc::new(RwQueue::new());
+    let guard = queue.write_arc().await;
+    let join = tokio::spawn(async move {
+        let _guard = guard;
+        sleep(Duration::from_millis(20)).await;
+    });
+    sleep(Duration::from_millis(1)).await;
+    assert!(queue.try_read_run(|_| async move {}, &()).await.is_err());
+    join.await?;
+    let guard = queue.read_arc().await;
+    tokio::spawn(async move {
+        let _guard = guard;
+    })
+    .await?;
+    assert_eq!(queue.try_write_run(|_| async move {}, &mut ()).await, Ok(()));
+
+    // lock inside the call of the model
+    let res = model.call(async |_| model.checked_write().await.err()).await;
+    assert_eq!(res, Some(aqueue::Error::Reentrant));
+
+    // the panic while holding the write guard poison the model
+    let a_model = model.clone();
+    let join = tokio::spawn(async move {
+        let mut guard = a_model.write().await;
+        guard.clear();
+        panic!("boom")
+    });
+    assert!(join.await.unwrap_err().is_panic());
+    assert!(model.is_poisoned());
+    assert_eq!(model.checked_read().await.err(), Some(aqueue::Error::Poisoned));
+    model.clear_poison();
+    assert!(model.read().await.is_empty());
+    model.close();
+    assert_eq!(model.checked_write().await.err(), Some(aqueue::Error::Closed));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_upgradable() -> Result<()> {
+    let model = Arc::new(RwModel::new(std::collections::HashMap::<u32, u32>::new()));
+
+    // look up the cache and insert if missing
+    for _ in 0..3 {
+        let value = model
+            .call_upgradable(async move |mut inner| {
+                if let Some(value) = inner.get(&1) {
+                    return *value;
+                }
+                assert!(!inner.is_upgraded());
+                let mut inner = inner.upgrade().await;
+                *inner.entry(1).or_insert(10)
+            })
+            .await;
+        assert_eq!(value, 10);
+    }
+
+    // the upgradable call share the model with the read calls, the upgrade wait them finish
+    let a_model = model.clone();
+    let join = tokio::spawn(async move {
+        a_model
+            .call_upgradable(async move |mut inner| {
+                let value = inner[&1];
+                sleep(Duration::from_millis(20)).await;
+                inner.upgrade().await.insert(2, value + 1);
+            })
+            .await
+    });
+    sleep(Duration::from_millis(5)).await;
+    let start = Instant::now();
+    model
+        .call(async move |inner| {
+            assert_eq!(inner.get(&2), None);
+            sleep(Duration::from_millis(50)).await;
+        })
+        .await;
+    assert!(start.elapsed() < Duration::from_millis(100));
+    join.await?;
+    assert_eq!(model.call(async move |inner| { inner[&2] }).await, 11);
+
+    // the cancelled upgrade keep the lock, the inner can still be read and the next upgrade resume it
+    let a_model = model.clone();
+    let reader = tokio::spawn(async move { a_model.call(async move |_| sleep(Duration::from_millis(50)).await).await });
+    sleep(Duration::from_millis(10)).await;
+    let res = model
+        .call_upgradable(async |mut inner| {
+            assert!(tokio::time::timeout(Duration::from_millis(10), inner.upgrade()).await.is_err());
+            assert!(!inner.is_upgraded());
+            let a_model = model.clone();
+            let writer = tokio::spawn(async move { a_model.try_call_mut(async move |_| {}).await });
+            assert_eq!(writer.await.unwrap(), Err(aqueue::Error::WouldBlock));
+            let value = inner[&1];
+            inner.upgrade().await.insert(3, value);
+            inner[&3]
+        })
+        .await;
+    assert_eq!(res, model.call(async move |inner| { inner[&1] }).await);
+    reader.await?;
+
+    // at most one upgradable call, and the queue fn
+    let queue = RwQueue::new();
+    let mut value = 1;
+    let res = queue
+        .upgradable_read_run(
+            |mut inner| async move {
+                let read = *inner;
+                *inner.upgrade().await += read;
+                *inner.upgrade().await += read;
+                *inner
+            },
+            &mut value,
+        )
+        .await;
+    assert_eq!(res, 3);
+
+    // lock inside the call of the model
+    let res = model.call_upgradable(async |_| model.checked_call_upgradable(async |_| {}).await).await;
+    assert_eq!(res, Err(aqueue::Error::Reentrant));
+
+    // the panic before upgrade not poison the model, after upgrade poison it
+    let a_model = model.clone();
+    let join = tokio::spawn(async move { a_model.call_upgradable(async move |_| panic!("boom")).await });
+    assert!(join.await.unwrap_err().is_panic());
+    assert!(!model.is_poisoned());
+    let a_model = model.clone();
+    let join = tokio::spawn(async move {
+        a_model
+            .call_upgradable(async move |mut inner| {
+                inner.upgrade().await.clear();
+                panic!("boom")
+            })
+            .await
+    });
+    assert!(join.await.unwrap_err().is_panic());
+    assert!(model.is_poisoned());
+    assert_eq!(model.checked_call_upgradable(async move |_| {}).await, Err(aqueue::Error::Poisoned));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_policy() -> Result<()> {
+    use aqueue::RwPolicy;
+    use std::sync::Mutex;
+
+    // a reader hold the model, then a writer and a reader come
+    async fn order(policy: RwPolicy) -> Result<(Vec<&'static str>, bool)> {
+        let model = Arc::new(RwModel::with_policy((), policy));
+        assert_eq!(model.policy(), policy);
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        let a_model = model.clone();
+        let reader = tokio::spawn(async move { a_model.call(async move |_| sleep(Duration::from_millis(50)).await).await });
+        sleep(Duration::from_millis(10)).await;
+        let (a_model, a_log) = (model.clone(), log.clone());
+        let writer = tokio::spawn(async move { a_model.call_mut(async move |_| a_log.lock().unwrap().push("write")).await });
+        sleep(Duration::from_millis(10)).await;
+        // the try and sync calls honor the policy too
+        let try_read = model.try_call(async move |_| {}).await.is_ok();
+        let (a_model, a_log) = (model.clone(), log.clone());
+        let sync_reader = std::thread::spawn(move || a_model.sync_call(|_| a_log.lock().unwrap().push("sync read")));
+        let (a_model, a_log) = (model.clone(), log.clone());
+        let async_reader = tokio::spawn(async move { a_model.call(async move |_| a_log.lock().unwrap().push("read")).await });
+        sleep(Duration::from_millis(10)).await;
+        log.lock().unwrap().push("now");
+
+        reader.await?;
+        writer.await?;
+        async_reader.await?;
+        sync_reader.join().unwrap();
+        // the sync and async readers come at the same time
+        let log = log
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|event| if event.ends_with("read") { "read" } else { event })
+            .collect();
+        Ok((log, try_read))
+    }
+
+    let (log, try_read) = order(RwPolicy::WriterPreferring).await?;
+    assert_eq!(log[..2], ["now", "write"]);
+    assert!(!try_read);
+    let (log, try_read) = order(RwPolicy::ReaderPreferring).await?;
+    assert_eq!(log, ["read", "read", "now", "write"]);
+    assert!(try_read);
+    let (log, try_read) = order(RwPolicy::Fifo).await?;
+    assert_eq!(log, ["now", "write", "read", "read"]);
+    assert!(!try_read);
+
+    // first come, first run, a writer, a reader and a writer
+    let model = Arc::new(RwModel::with_policy(Vec::new(), RwPolicy::Fifo));
+    let a_model = model.clone();
+    let first = tokio::spawn(async move {
+        a_model
+            .call_mut(async move |mut inner| {
+                sleep(Duration::from_millis(30)).await;
+                inner.push(1)
+            })
+            .await
+    });
+    sleep(Duration::from_millis(10)).await;
+    let a_model = model.clone();
+    let reader = tokio::spawn(async move { a_model.call(async move |inner| inner.len()).await });
+    sleep(Duration::from_millis(10)).await;
+    let a_model = model.clone();
+    let second = tokio::spawn(async move { a_model.call_mut(async move |mut inner| inner.push(2)).await });
+    first.await?;
+    assert_eq!(reader.await?, 1);
+    second.await?;
+    assert_eq!(model.call(async move |inner| { inner.clone() }).await, [1, 2]);
+
+    // the writer of reader preferring wait the upgradable read leave
+    let model = Arc::new(RwModel::with_policy(0, RwPolicy::ReaderPreferring));
+    let a_model = model.clone();
+    let upgradable = tokio::spawn(async move {
+        a_model
+            .call_upgradable(async move |mut inner| {
+                sleep(Duration::from_millis(20)).await;
+                *inner.upgrade().await += 1;
+            })
+            .await
+    });
+    sleep(Duration::from_millis(5)).await;
+    assert_eq!(model.call(async move |inner| { *inner }).await, 0);
+    model.call_mut(async move |mut inner| *inner *= 10).await;
+    upgradable.await?;
+    assert_eq!(model.call(async move |inner| { *inner }).await, 10);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_subscribe() -> Result<()> {
+    use futures_util::StreamExt;
+
+    let model = Arc::new(RwModel::new(Vec::new()));
+    let mut versions = model.subscribe();
+    let mut watch = model.watch(|inner| inner.iter().sum::<i32>());
+
+    // wake the waiting listener
+    let a_model = model.clone();
+    let writer = tokio::spawn(async move {
+        sleep(Duration::from_millis(10)).await;
+        a_model.call_mut(async move |mut inner| inner.push(1)).await;
+    });
+    assert_eq!(versions.next().await, Some(1));
+    assert_eq!(watch.next().await, Some((1, 1)));
+    writer.await?;
+
+    // the slow consumer only get the latest
+    for i in 2..=4 {
+        model.call_mut(async move |mut inner| inner.push(i)).await;
+    }
+    model.sync_mut_call(|mut inner| inner.push(5));
+    assert_eq!(model.version(), 5);
+    assert_eq!(versions.next().await, Some(5));
+    assert_eq!(watch.next().await, Some((5, 15)));
+
+    // the read calls, the upgradable call not upgraded and the panicked write call not change it
+    model.call(async move |inner| inner.len()).await;
+    model.call_upgradable(async move |inner| inner.len()).await;
+    let a_model = model.clone();
+    let join = tokio::spawn(async move { a_model.call_mut(async move |_| panic!("boom")).await });
+    assert!(join.await.unwrap_err().is_panic());
+    model.clear_poison();
+    assert_eq!(model.version(), 5);
+    {
+        let mut guard = model.write().await;
+        guard.push(6);
+    }
+    assert_eq!(versions.next().await, Some(6));
+    assert_eq!(watch.next().await, Some((6, 21)));
+
+    // end if the model is closed
+    model.close();
+    assert_eq!(versions.next().await, None);
+    assert_eq!(watch.next().await, None);
+    Ok(())
+}
@@ -89,3 +89,252 @@ async fn test_pc_model() {
 
     assert!(now.elapsed().as_secs() >= 2 && now.elapsed().as_secs() < 3);
 }
+
+#[tokio::test]
+async fn test_poison() {
+    let model = Arc::new(PCModel::new(std::sync::atomic::AtomicI32::new(0), 2));
+    let a_model = model.clone();
+    let join = tokio::spawn(async move {
+        a_model
+            .call(|inner| async move {
+                inner.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                panic!("boom")
+            })
+            .await
+    });
+    assert!(join.await.unwrap_err().is_panic());
+    assert!(model.is_poisoned());
+    assert_eq!(model.checked_call(|_| async move {}).await, Err(aqueue::Error::Poisoned));
+    model.clear_poison();
+    assert_eq!(
+        model
+            .checked_call(|inner| async move { inner.load(std::sync::atomic::Ordering::SeqCst) })
+            .await,
+        Ok(1)
+    );
+}
+
+#[tokio::test]
+async fn test_close() {
+    let model = PCModel::new(1, 2);
+    assert_eq!(model.checked_call(|inner| async move { *inner }).await, Ok(1));
+    model.close();
+    assert!(model.is_closed());
+    assert_eq!(model.checked_call(|inner| async move { *inner }).await, Err(aqueue::Error::Closed));
+}
+
+#[tokio::test]
+async fn test_timeout() {
+    use std::time::{Duration, Instant};
+    let model = PCModel::new(1, 1);
+    assert_eq!(
+        model
+            .call_timeout(Duration::from_millis(10), |inner| async move {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                *inner
+            })
+            .await,
+        Err(aqueue::Error::Timeout)
+    );
+    assert_eq!(
+        model
+            .call_timeout(Instant::now() + Duration::from_millis(100), |inner| async move { *inner })
+            .await,
+        Ok(1)
+    );
+}
+
+#[tokio::test]
+async fn test_acquire_timeout() {
+    use std::time::Duration;
+    let model = Arc::new(PCModel::new(1, 1));
+    let a_model = model.clone();
+    let running = tokio::spawn(async move {
+        a_model
+            .call(|_| async move { tokio::time::sleep(Duration::from_millis(100)).await })
+            .await
+    });
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    assert_eq!(
+        model.call_acquire_timeout(Duration::from_millis(10), |inner| async move { *inner }).await,
+        Err(aqueue::Error::Timeout)
+    );
+    running.await.unwrap();
+    assert_eq!(
+        model
+            .call_acquire_timeout(Duration::from_millis(10), |inner| async move {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                *inner
+            })
+            .await,
+        Ok(1)
+    );
+}
+
+#[tokio::test]
+async fn test_cancel() {
+    use aqueue::CancelPolicy;
+    use std::time::Duration;
+    use tokio::time::sleep;
+
+    let model = Arc::new(PCModel::new(1, 1));
+    let a_model = model.clone();
+    let running = tokio::spawn(async move { a_model.call(|_| async move { sleep(Duration::from_millis(100)).await }).await });
+    sleep(Duration::from_millis(10)).await;
+    assert_eq!(
+        model
+            .call_with_cancel(sleep(Duration::from_millis(10)), CancelPolicy::OnlyWaiting, |inner| async move { *inner })
+            .await,
+        Err(aqueue::Error::Cancelled)
+    );
+    running.await.unwrap();
+    assert_eq!(
+        model
+            .call_with_cancel(sleep(Duration::from_millis(10)), CancelPolicy::AlsoRunning, |inner| async move {
+                sleep(Duration::from_millis(50)).await;
+                *inner
+            })
+            .await,
+        Err(aqueue::Error::Cancelled)
+    );
+    assert!(!model.is_poisoned());
+}
+
+#[tokio::test]
+async fn test_bounded() {
+    use aqueue::FullPolicy;
+    use std::time::Duration;
+    use tokio::time::sleep;
+
+    let model = Arc::new(PCModel::bounded(0, 2, 1, FullPolicy::Reject));
+    let mut joins = vec![];
+    for _ in 0..3 {
+        let a_model = model.clone();
+        joins.push(tokio::spawn(async move {
+            a_model.checked_call(|_| async move { sleep(Duration::from_millis(50)).await }).await
+        }));
+        sleep(Duration::from_millis(10)).await;
+    }
+    // two running, one pending
+    assert_eq!(model.checked_call(|inner| async move { *inner }).await, Err(aqueue::Error::Full));
+    for join in joins {
+        assert_eq!(join.await.unwrap(), Ok(()));
+    }
+    assert_eq!(model.checked_call(|inner| async move { *inner }).await, Ok(0));
+}
+
+#[tokio::test]
+async fn test_try_call() {
+    use std::time::Duration;
+    use tokio::time::sleep;
+
+    let model = Arc::new(PCModel::new(0, 1));
+    let a_model = model.clone();
+    let running = tokio::spawn(async move { a_model.call(|_| async move { sleep(Duration::from_millis(50)).await }).await });
+    sleep(Duration::from_millis(10)).await;
+    assert_eq!(model.try_call(|inner| async move { *inner }).await, Err(aqueue::Error::WouldBlock));
+    running.await.unwrap();
+    assert_eq!(model.try_call(|inner| async move { *inner }).await, Ok(0));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_block_call() {
+    let model = Arc::new(PCModel::new(std::sync::atomic::AtomicU32::new(0), 1));
+    let a_model = model.clone();
+    let running = tokio::spawn(async move {
+        a_model
+            .call(|inner| async move {
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+                inner.store(1, std::sync::atomic::Ordering::Release);
+            })
+            .await
+    });
+    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    let a_model = model.clone();
+    let block_call = std::thread::spawn(move || {
+        let value = a_model.sync_call(|inner| inner.load(std::sync::atomic::Ordering::Acquire));
+        let block_value = a_model.block_call(|inner| async move { inner.fetch_add(1, std::sync::atomic::Ordering::AcqRel) + 1 });
+        (value, block_value)
+    });
+    running.await.unwrap();
+    assert_eq!(block_call.join().unwrap(), (1, 2));
+
+    assert_eq!(model.checked_sync_call(|inner| inner.load(std::sync::atomic::Ordering::Acquire)), Ok(2));
+
+    // the blocking pool thread of the blocking call park like a plain thread
+    let a_model = model.clone();
+    let running = tokio::spawn(async move {
+        a_model
+            .call(|_| async move { tokio::time::sleep(std::time::Duration::from_millis(50)).await })
+            .await
+    });
+    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    let a_model = model.clone();
+    let sync_call = tokio::spawn(async move {
+        aqueue::AQueue::new()
+            .run_blocking(
+                move |_| a_model.checked_sync_call(|inner| inner.fetch_add(1, std::sync::atomic::Ordering::AcqRel) + 1),
+                (),
+            )
+            .await
+    });
+    running.await.unwrap();
+    assert_eq!(sync_call.await.unwrap(), Ok(Ok(3)));
+}
+
+#[tokio::test]
+async fn test_call_blocking() {
+    let model = Arc::new(PCModel::new(std::sync::atomic::AtomicU32::new(0), 2));
+    let now = std::time::Instant::now();
+    let mut tasks = vec![];
+    for _ in 0..4 {
+        let model = model.clone();
+        tasks.push(tokio::spawn(async move {
+            model
+                .call_blocking(|inner| {
+                    std::thread::sleep(std::time::Duration::from_millis(50));
+                    inner.fetch_add(1, std::sync::atomic::Ordering::AcqRel)
+                })
+                .await
+        }));
+    }
+    for task in tasks {
+        assert!(task.await.unwrap().is_ok());
+    }
+    // at most 2 blocking calls run at the same time
+    assert!(now.elapsed() >= std::time::Duration::from_millis(100));
+    assert_eq!(model.inner().load(std::sync::atomic::Ordering::Acquire), 4);
+}
+
+#[tokio::test]
+async fn test_async_closure() {
+    let model = PCModel::new(vec![1, 2, 3], 2);
+    let index = 1;
+    assert_eq!(model.call(async |inner| inner[index]).await, 2);
+    assert_eq!(model.checked_call(async |inner| inner.len()).await, Ok(3));
+}
+
+#[tokio::test]
+async fn test_lock_guard() {
+    let queue = Arc::new(SemaphoreQueue::new(2));
+
+    // the guards hold the permits over multiple awaits
+    let a = queue.acquire().await;
+    let b = queue.acquire_arc().await;
+    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    assert!(queue.try_run(|_| async move {}, ()).await.is_err());
+    drop(a);
+    assert_eq!(queue.try_run(|_| async move {}, ()).await, Ok(()));
+
+    // move the owned guard into the spawned task, the panic while holding the guard poison the queue
+    let join = tokio::spawn(async move {
+        let _guard = b;
+        panic!("boom")
+    });
+    assert!(join.await.unwrap_err().is_panic());
+    assert!(queue.is_poisoned());
+    assert_eq!(queue.checked_acquire().await.err(), Some(aqueue::Error::Poisoned));
+    queue.clear_poison();
+    queue.close();
+    assert_eq!(queue.checked_acquire_arc().await.err(), Some(aqueue::Error::Closed));
+}
@@ -0,0 +1,87 @@
+use anyhow::Result;
+use aqueue::SnapshotModel;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::time::{sleep, Duration};
+
+#[tokio::test]
+async fn test_base() -> Result<()> {
+    let model = Arc::new(SnapshotModel::new(0u64));
+    let mut joins = Vec::new();
+    for _ in 0..100 {
+        let a_model = model.clone();
+        joins.push(tokio::spawn(async move {
+            for _ in 0..100 {
+                a_model.call_mut(async move |mut inner| *inner += 1).await;
+            }
+        }));
+    }
+    for join in joins {
+        join.await?;
+    }
+    assert_eq!(*model.snapshot(), 10000);
+    assert_eq!(model.call(async move |inner| *inner).await, 10000);
+    assert_eq!(model.sync_call(|inner| *inner), 10000);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_read_not_wait() -> Result<()> {
+    let model = Arc::new(SnapshotModel::new(HashMap::from([(1, 1)])));
+    let old = model.snapshot();
+
+    // the readers see the old state while the write call is running
+    let a_model = model.clone();
+    let writer = tokio::spawn(async move {
+        a_model
+            .call_mut(async move |mut inner| {
+                inner.insert(2, 2);
+                sleep(Duration::from_millis(50)).await;
+                inner.insert(3, 3);
+            })
+            .await
+    });
+    sleep(Duration::from_millis(10)).await;
+    let start = Instant::now();
+    assert_eq!(model.call(async move |inner| inner.len()).await, 1);
+    let a_model = model.clone();
+    assert_eq!(std::thread::spawn(move || a_model.sync_call(|inner| inner.len())).join().unwrap(), 1);
+    assert!(start.elapsed() < Duration::from_millis(30));
+    writer.await?;
+
+    // publish at once, the old snapshot is not changed
+    assert_eq!(model.call(async move |inner| inner.len()).await, 3);
+    assert_eq!(old.len(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_poison() -> Result<()> {
+    let model = Arc::new(SnapshotModel::new(vec![1]));
+    let a_model = model.clone();
+    let join = tokio::spawn(async move {
+        a_model
+            .call_mut(async move |mut inner| {
+                inner.clear();
+                panic!("boom")
+            })
+            .await
+    });
+    assert!(join.await.unwrap_err().is_panic());
+
+    // the panic publish nothing
+    assert!(model.is_poisoned());
+    assert_eq!(*model.snapshot(), [1]);
+    assert_eq!(model.checked_call_mut(async move |_| {}).await, Err(aqueue::Error::Poisoned));
+    model.clear_poison();
+
+    // write again inside the write call
+    let res = model.call_mut(async |_| model.checked_call_mut(async |_| {}).await).await;
+    assert_eq!(res, Err(aqueue::Error::Reentrant));
+
+    model.close();
+    assert_eq!(model.checked_call_mut(async move |_| {}).await, Err(aqueue::Error::Closed));
+    assert_eq!(model.call(async move |inner| inner.len()).await, 1);
+    Ok(())
+}